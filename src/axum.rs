@@ -10,8 +10,9 @@ use crate::de::Config as QsConfig;
 use crate::error::Error as QsError;
 
 use axum::{
-    extract::{Extension, FromRequestParts},
-    http::StatusCode,
+    body::Bytes,
+    extract::{Extension, FromRequest, FromRequestParts, Request},
+    http::{header, Method, StatusCode},
     response::{IntoResponse, Response},
     BoxError, Error,
 };
@@ -96,6 +97,106 @@ where
     }
 }
 
+#[derive(Clone, Copy, Default)]
+/// Extract typed information from the request's form body, or from its query
+/// for `GET` and `HEAD` requests.
+///
+/// Rejections are the same as for [`QsQuery`], and a [`QsQueryConfig`]
+/// extension configures both.
+///
+/// As a response, the value is serialized with `Content-Type:
+/// application/x-www-form-urlencoded`.
+///
+/// ## Example
+///
+/// ```rust
+/// # extern crate axum_framework as axum;
+/// use serde_qs::axum::QsForm;
+/// use axum::{response::IntoResponse, routing::post, Router};
+///
+/// #[derive(serde::Deserialize)]
+/// pub struct UsersFilter {
+///    id: Vec<u64>,
+/// }
+///
+/// // The correct request payload for this handler would be `id[]=1124&id[]=88`
+/// async fn filter_users(
+///     QsForm(info): QsForm<UsersFilter>
+/// ) -> impl IntoResponse {
+///     info.id
+///         .iter()
+///         .map(|i| i.to_string())
+///         .collect::<Vec<String>>()
+///         .join(", ")
+/// }
+///
+/// fn main() {
+///     let app = Router::<()>::new()
+///         .route("/users", post(filter_users));
+/// }
+/// ```
+pub struct QsForm<T>(pub T);
+
+impl<T> std::ops::Deref for QsForm<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for QsForm<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[axum::async_trait]
+impl<T, S> FromRequest<S> for QsForm<T>
+where
+    T: serde::de::DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = QsQueryRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let (mut parts, body) = req.into_parts();
+        let Extension(qs_config) =
+            Extension::<QsQueryConfig>::from_request_parts(&mut parts, state)
+                .await
+                .unwrap_or_else(|_| Extension(QsQueryConfig::default()));
+        let error_handler = qs_config.error_handler.clone();
+        let config: QsConfig = qs_config.into();
+        let result = if parts.method == Method::GET || parts.method == Method::HEAD {
+            config.deserialize_str::<T>(parts.uri.query().unwrap_or_default())
+        } else {
+            let bytes = Bytes::from_request(Request::from_parts(parts, body), state)
+                .await
+                .map_err(|err| {
+                    let status = err.status();
+                    QsQueryRejection::new(err, status)
+                })?;
+            config.deserialize_bytes::<T>(&bytes)
+        };
+        match result {
+            Ok(value) => Ok(QsForm(value)),
+            Err(err) => match error_handler {
+                Some(handler) => Err((handler)(err)),
+                None => Err(QsQueryRejection::new(err, StatusCode::BAD_REQUEST)),
+            },
+        }
+    }
+}
+
+impl<T: serde::Serialize> IntoResponse for QsForm<T> {
+    fn into_response(self) -> Response {
+        match crate::to_string(&self.0) {
+            Ok(body) => ([(header::CONTENT_TYPE, crate::FORM_CONTENT_TYPE)], body).into_response(),
+            Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+        }
+    }
+}
+
 #[derive(Debug)]
 /// Rejection type for extractors that deserialize query strings
 pub struct QsQueryRejection {
@@ -0,0 +1,639 @@
+//! Deserializing a querystring on top of an existing value, for
+//! `from_str_with_base`.
+//!
+//! The base is captured from its `Serialize` implementation without going
+//! through a querystring, so that e.g. empty sequences and `Some("")` are
+//! kept, and merged with the parsed querystring as it is deserialized.
+
+use super::*;
+
+use serde::de;
+use serde::ser;
+
+/// A value captured from a `Serialize` implementation, which deserializes
+/// back into the same value.
+pub(crate) enum Captured {
+    Unit,
+    Bool(bool),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Char(char),
+    String(String),
+    Bytes(Vec<u8>),
+    None,
+    Some(Box<Captured>),
+    Seq(Vec<Captured>),
+    Map(Vec<(Captured, Captured)>),
+    /// An enum variant and its value: `Unit` for a unit variant, a `Seq`
+    /// for a tuple variant and a `Map` for a struct variant.
+    Variant(&'static str, Box<Captured>),
+}
+
+impl Captured {
+    pub(crate) fn capture<T: ser::Serialize>(value: &T) -> Result<Self> {
+        value.serialize(CaptureSerializer)
+    }
+
+    /// The key this value matches in a querystring, if it can be a key.
+    fn as_key(&self) -> Option<Cow<'_, str>> {
+        match *self {
+            Captured::String(ref s) => Some(Cow::Borrowed(s)),
+            Captured::Char(c) => Some(Cow::Owned(c.to_string())),
+            Captured::Bool(b) => Some(Cow::Owned(b.to_string())),
+            Captured::I64(i) => Some(Cow::Owned(i.to_string())),
+            Captured::U64(u) => Some(Cow::Owned(u.to_string())),
+            Captured::Variant(name, ref value) if matches!(**value, Captured::Unit) => {
+                Some(Cow::Borrowed(name))
+            }
+            _ => None,
+        }
+    }
+}
+
+struct CaptureSerializer;
+
+macro_rules! capture_primitives {
+    ($($method:ident($ty:ty) => $variant:ident,)*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<Captured> {
+                Ok(Captured::$variant(v.into()))
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for CaptureSerializer {
+    type Ok = Captured;
+    type Error = Error;
+    type SerializeSeq = CaptureSeq;
+    type SerializeTuple = CaptureSeq;
+    type SerializeTupleStruct = CaptureSeq;
+    type SerializeTupleVariant = CaptureSeq;
+    type SerializeMap = CaptureMap;
+    type SerializeStruct = CaptureMap;
+    type SerializeStructVariant = CaptureMap;
+
+    capture_primitives! {
+        serialize_bool(bool) => Bool,
+        serialize_i8(i8) => I64,
+        serialize_i16(i16) => I64,
+        serialize_i32(i32) => I64,
+        serialize_i64(i64) => I64,
+        serialize_u8(u8) => U64,
+        serialize_u16(u16) => U64,
+        serialize_u32(u32) => U64,
+        serialize_u64(u64) => U64,
+        serialize_f32(f32) => F64,
+        serialize_f64(f64) => F64,
+        serialize_char(char) => Char,
+        serialize_str(&str) => String,
+        serialize_bytes(&[u8]) => Bytes,
+    }
+
+    fn serialize_none(self) -> Result<Captured> {
+        Ok(Captured::None)
+    }
+
+    fn serialize_some<T: ?Sized + ser::Serialize>(self, value: &T) -> Result<Captured> {
+        Ok(Captured::Some(Box::new(value.serialize(self)?)))
+    }
+
+    fn serialize_unit(self) -> Result<Captured> {
+        Ok(Captured::Unit)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Captured> {
+        Ok(Captured::Unit)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Captured> {
+        Ok(Captured::Variant(variant, Box::new(Captured::Unit)))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + ser::Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Captured> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + ser::Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Captured> {
+        Ok(Captured::Variant(variant, Box::new(value.serialize(self)?)))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<CaptureSeq> {
+        Ok(CaptureSeq(None, Vec::with_capacity(len.unwrap_or(0))))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<CaptureSeq> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<CaptureSeq> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<CaptureSeq> {
+        Ok(CaptureSeq(Some(variant), Vec::with_capacity(len)))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<CaptureMap> {
+        Ok(CaptureMap(None, Vec::with_capacity(len.unwrap_or(0)), None))
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<CaptureMap> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<CaptureMap> {
+        Ok(CaptureMap(Some(variant), Vec::with_capacity(len), None))
+    }
+}
+
+/// The elements of a sequence, and the variant it belongs to if any.
+struct CaptureSeq(Option<&'static str>, Vec<Captured>);
+
+impl CaptureSeq {
+    fn push<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<()> {
+        self.1.push(Captured::capture(&value)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Captured> {
+        let seq = Captured::Seq(self.1);
+        Ok(match self.0 {
+            Some(variant) => Captured::Variant(variant, Box::new(seq)),
+            None => seq,
+        })
+    }
+}
+
+impl ser::SerializeSeq for CaptureSeq {
+    type Ok = Captured;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Captured> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for CaptureSeq {
+    type Ok = Captured;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Captured> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for CaptureSeq {
+    type Ok = Captured;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Captured> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for CaptureSeq {
+    type Ok = Captured;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Captured> {
+        self.finish()
+    }
+}
+
+/// The entries of a map or struct, the variant it belongs to if any, and
+/// the key waiting for its value.
+struct CaptureMap(
+    Option<&'static str>,
+    Vec<(Captured, Captured)>,
+    Option<Captured>,
+);
+
+impl CaptureMap {
+    fn field<T: ?Sized + ser::Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        let value = Captured::capture(&value)?;
+        self.1.push((Captured::String(key.to_owned()), value));
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Captured> {
+        let map = Captured::Map(self.1);
+        Ok(match self.0 {
+            Some(variant) => Captured::Variant(variant, Box::new(map)),
+            None => map,
+        })
+    }
+}
+
+impl ser::SerializeMap for CaptureMap {
+    type Ok = Captured;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + ser::Serialize>(&mut self, key: &T) -> Result<()> {
+        self.2 = Some(Captured::capture(&key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<()> {
+        let key = self
+            .2
+            .take()
+            .ok_or_else(|| Error::Custom("map value serialized before its key".to_owned()))?;
+        self.1.push((key, Captured::capture(&value)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Captured> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for CaptureMap {
+    type Ok = Captured;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + ser::Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Captured> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for CaptureMap {
+    type Ok = Captured;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + ser::Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Captured> {
+        self.finish()
+    }
+}
+
+impl<'de> de::IntoDeserializer<'de, Error> for Captured {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Captured {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Captured::Unit => visitor.visit_unit(),
+            Captured::Bool(b) => visitor.visit_bool(b),
+            Captured::I64(i) => visitor.visit_i64(i),
+            Captured::U64(u) => visitor.visit_u64(u),
+            Captured::F64(f) => visitor.visit_f64(f),
+            Captured::Char(c) => visitor.visit_char(c),
+            Captured::String(s) => visitor.visit_string(s),
+            Captured::Bytes(b) => visitor.visit_byte_buf(b),
+            Captured::None => visitor.visit_none(),
+            Captured::Some(value) => visitor.visit_some(*value),
+            Captured::Seq(seq) => {
+                visitor.visit_seq(de::value::SeqDeserializer::new(seq.into_iter()))
+            }
+            Captured::Map(map) => {
+                visitor.visit_map(de::value::MapDeserializer::new(map.into_iter()))
+            }
+            Captured::Variant(variant, value) => {
+                visitor.visit_enum(CapturedVariant(variant, *value))
+            }
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Captured::None | Captured::Unit => visitor.visit_none(),
+            Captured::Some(value) => visitor.visit_some(*value),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Captured::String(s) => {
+                visitor.visit_enum(de::IntoDeserializer::<Error>::into_deserializer(s))
+            }
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string unit
+        bytes byte_buf unit_struct seq tuple tuple_struct map struct identifier
+        ignored_any
+    }
+}
+
+/// An enum variant and its value, as captured.
+struct CapturedVariant(&'static str, Captured);
+
+impl<'de> de::EnumAccess<'de> for CapturedVariant {
+    type Error = Error;
+    type Variant = Captured;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Captured)>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(de::IntoDeserializer::<Error>::into_deserializer(self.0))?;
+        Ok((variant, self.1))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Captured {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}
+
+/// A value from the querystring, the base, or both.
+pub(crate) enum Merged<'a> {
+    Level(Level<'a>, Config),
+    Base(Captured),
+    Both(Level<'a>, Captured, Config),
+}
+
+/// How a `Merged` value is deserialized, once known not to be an option.
+enum Resolved<'a> {
+    Level(Level<'a>, Config),
+    Base(Captured),
+    /// Maps present in both are merged key by key.
+    Map(
+        IndexMap<Cow<'a, str>, Level<'a>>,
+        Vec<(Captured, Captured)>,
+        Config,
+    ),
+}
+
+impl<'a> Merged<'a> {
+    fn resolve(self) -> Resolved<'a> {
+        match self {
+            Merged::Level(level, config) => Resolved::Level(level, config),
+            Merged::Base(base) => Resolved::Base(base),
+            Merged::Both(Level::Nested(map), Captured::Map(entries), config) => {
+                Resolved::Map(map, entries, config)
+            }
+            // Any other value in the querystring replaces the base.
+            Merged::Both(level, _, config) => Resolved::Level(level, config),
+        }
+    }
+}
+
+macro_rules! deserialize_resolved {
+    ($($method:ident($($arg:ident: $ty:ty),*),)*) => {
+        $(
+            fn $method<V>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value>
+            where
+                V: de::Visitor<'de>,
+            {
+                match self.resolve() {
+                    Resolved::Level(level, config) => {
+                        LevelDeserializer(level, config).$method($($arg,)* visitor)
+                    }
+                    Resolved::Base(base) => base.$method($($arg,)* visitor),
+                    Resolved::Map(map, entries, config) => {
+                        visitor.visit_map(MergedMap::new(map, entries, config))
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Merged<'de> {
+    type Error = Error;
+
+    /// A querystring value merged with `Some` of the base is merged with the
+    /// inner value.
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Merged::Both(level @ Level::Nested(_), Captured::Some(base), config) => {
+                visitor.visit_some(Merged::Both(level, *base, config))
+            }
+            Merged::Both(level, _, config) | Merged::Level(level, config) => {
+                LevelDeserializer(level, config).deserialize_option(visitor)
+            }
+            Merged::Base(base) => base.deserialize_option(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    deserialize_resolved! {
+        deserialize_any(),
+        deserialize_bool(),
+        deserialize_i8(),
+        deserialize_i16(),
+        deserialize_i32(),
+        deserialize_i64(),
+        deserialize_u8(),
+        deserialize_u16(),
+        deserialize_u32(),
+        deserialize_u64(),
+        deserialize_f32(),
+        deserialize_f64(),
+        deserialize_char(),
+        deserialize_str(),
+        deserialize_string(),
+        deserialize_bytes(),
+        deserialize_byte_buf(),
+        deserialize_unit(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_seq(),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_map(),
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]),
+        deserialize_identifier(),
+        deserialize_ignored_any(),
+    }
+}
+
+/// A key from the base, which keeps its type, or from the querystring.
+enum MergedKey<'a> {
+    Base(Captured),
+    Level(Cow<'a, str>),
+}
+
+/// The entries of the base, merged with those of the querystring with the
+/// same key, followed by the remaining entries of the querystring.
+struct MergedMap<'a> {
+    entries: std::vec::IntoIter<(MergedKey<'a>, Merged<'a>)>,
+    value: Option<Merged<'a>>,
+    config: Config,
+}
+
+impl<'a> MergedMap<'a> {
+    fn new(
+        mut map: IndexMap<Cow<'a, str>, Level<'a>>,
+        base: Vec<(Captured, Captured)>,
+        config: Config,
+    ) -> Self {
+        let mut entries = Vec::with_capacity(base.len() + map.len());
+        for (key, value) in base {
+            let level = key.as_key().and_then(|key| map.shift_remove(key.as_ref()));
+            let value = match level {
+                Some(level) => Merged::Both(level, value, config),
+                None => Merged::Base(value),
+            };
+            entries.push((MergedKey::Base(key), value));
+        }
+        for (key, level) in map {
+            entries.push((MergedKey::Level(key), Merged::Level(level, config)));
+        }
+        MergedMap {
+            entries: entries.into_iter(),
+            value: None,
+            config,
+        }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for MergedMap<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                match key {
+                    MergedKey::Base(key) => seed.deserialize(key),
+                    MergedKey::Level(key) => {
+                        seed.deserialize(ParsableStringDeserializer(key, self.config))
+                    }
+                }
+                .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::custom(
+                "Somehow the map was empty after a non-empty key was returned",
+            )),
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! `Serialize` and `Deserialize` implementations for [`Level`], so the
+//! intermediate tree can be inspected using other formats such as JSON.
+//!
+//! Enable with the `level-serde` feature.
+
+use super::Level;
+
+use indexmap::IndexMap;
+use serde::de;
+use serde::ser::{self, SerializeMap, SerializeSeq};
+
+use std::borrow::Cow;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// `Flat` values are written as strings, `Nested` levels as maps, and both
+/// kinds of sequence as sequences in index order. Levels holding no value,
+/// such as those which failed to parse, are written as unit (e.g. `null` in
+/// JSON), so the reason a value was invalid is not preserved.
+impl ser::Serialize for Level<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        match *self {
+            Level::Nested(ref map) => {
+                let mut state = serializer.serialize_map(Some(map.len()))?;
+                for (key, value) in map {
+                    state.serialize_entry(key, value)?;
+                }
+                state.end()
+            }
+            Level::OrderedSeq(ref map) => {
+                let mut state = serializer.serialize_seq(Some(map.len()))?;
+                for value in map.values() {
+                    state.serialize_element(value)?;
+                }
+                state.end()
+            }
+            Level::Sequence(ref seq) => {
+                let mut state = serializer.serialize_seq(Some(seq.len()))?;
+                for value in seq {
+                    state.serialize_element(value)?;
+                }
+                state.end()
+            }
+            Level::Flat(ref value) => serializer.serialize_str(value),
+            Level::Invalid(_) | Level::Uninitialised => serializer.serialize_unit(),
+        }
+    }
+}
+
+/// The inverse of the `Serialize` implementation: strings become `Flat`,
+/// maps `Nested` and sequences `Sequence`, while unit values become a level
+/// holding no value. Other primitives, such as numbers and booleans, are
+/// converted to `Flat` strings.
+impl<'de, 'a> de::Deserialize<'de> for Level<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(LevelVisitor(PhantomData))
+    }
+}
+
+struct LevelVisitor<'a>(PhantomData<Level<'a>>);
+
+macro_rules! visit_as_flat {
+    ($($ty:ty => $meth:ident,)*) => {
+        $(
+            fn $meth<E>(self, v: $ty) -> Result<Self::Value, E> {
+                Ok(Level::Flat(Cow::Owned(v.to_string())))
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> de::Visitor<'de> for LevelVisitor<'a> {
+    type Value = Level<'a>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a querystring level")
+    }
+
+    visit_as_flat! {
+        bool => visit_bool,
+        i64 => visit_i64,
+        u64 => visit_u64,
+        f64 => visit_f64,
+        &str => visit_str,
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(Level::Flat(Cow::Owned(v)))
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(Level::Uninitialised)
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(Level::Uninitialised)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        de::Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut values = Vec::new();
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Level::Sequence(values))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let mut values = IndexMap::new();
+        while let Some((key, value)) = map.next_entry::<String, _>()? {
+            values.insert(Cow::Owned(key), value);
+        }
+        Ok(Level::Nested(values))
+    }
+}
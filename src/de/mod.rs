@@ -36,15 +36,34 @@
 //! `Level` is a flat value it will attempt to deserialize it to a primitive via
 //! `ParsableStringDeserializer`.
 
+mod base;
+#[cfg(feature = "level-serde")]
+mod level_serde;
 mod parse;
+mod recover;
+mod traverse;
 
 use crate::error::*;
+use crate::ser::ArrayFormat;
+use crate::utils::{replace_space, QS_ENCODE_SET};
 
+use indexmap::map::{IndexMap, IntoIter};
+use percent_encoding::AsciiSet;
+
+use percent_encoding::percent_encode;
 use serde::de;
 use serde::de::IntoDeserializer;
+use serde::ser;
 
 use std::borrow::Cow;
-use std::collections::btree_map::{BTreeMap, Entry, IntoIter};
+use std::cell::RefCell;
+use std::collections::btree_map::{BTreeMap, Entry};
+use std::collections::{BTreeSet, HashMap};
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::Read;
+
+pub use self::traverse::{traverse, LevelVisitor};
 
 /// To override the default serialization parameters, first construct a new
 /// Config.
@@ -84,16 +103,145 @@ use std::collections::btree_map::{BTreeMap, Entry, IntoIter};
 pub struct Config {
     /// Specifies the maximum depth key that `serde_qs` will attempt to
     /// deserialize. Default is 5.
-    max_depth: usize,
+    pub(crate) max_depth: usize,
     /// Strict deserializing mode will not tolerate encoded brackets.
     strict: bool,
+    /// How flat values are presented to the deserialized type.
+    coercion: Coercion,
+    /// Key holding the variant name of enums, if any.
+    enum_tag_key: Option<&'static str>,
+    /// Maps variant names in the querystring to those of the enum, if set.
+    variant_lookup: Option<fn(&str) -> Option<&'static str>>,
+    /// Characters which are percent-encoded when serializing.
+    pub(crate) encoding_set: &'static AsciiSet,
+    /// Whether a leading UTF-8 byte order mark is ignored.
+    strip_bom: bool,
+    /// Maximum number of bracketed segments in a key, if any.
+    bracket_depth_limit: Option<usize>,
+    /// Maximum nesting depth of serialized values.
+    pub(crate) serialize_depth_limit: usize,
+    /// Largest sequence index, if any.
+    array_limit: Option<usize>,
+    /// Maximum number of distinct top-level keys.
+    key_limit: usize,
+    /// Maximum number of values across all keys.
+    pub(crate) max_value_count: usize,
+    /// Maximum length in bytes of any single value.
+    value_length_limit: usize,
+    /// Maximum length in bytes of any single key.
+    key_length_limit: usize,
+    /// What to do with top-level values which fail to deserialize.
+    error_policy: ErrorPolicy,
+    /// Whether invalid UTF-8 is an error, overriding `strict` if set.
+    strict_utf8: Option<bool>,
+    /// How sequences are written when serializing.
+    pub(crate) array_format: ArrayFormat,
+    /// Whether a `?` is written before the querystring when serializing.
+    pub(crate) leading_question_mark: bool,
+    /// Whether keys and values are percent-decoded when parsing.
+    percent_decode: bool,
+    /// Whether `+` is decoded as a space when parsing.
+    plus_as_space: bool,
+    /// Whether empty values deserialize as `None` rather than `Some("")`.
+    empty_string_as_none: bool,
+    /// Values accepted as `true` and `false` respectively.
+    bool_strings: (&'static [&'static str], &'static [&'static str]),
+    /// What to do with a key which appears more than once.
+    repeated_keys: RepeatedKeys,
+    /// Byte separating pairs when parsing.
+    pair_separator: u8,
+    /// Byte separating a key from its value when parsing.
+    key_value_separator: u8,
+    /// The order in which the keys of a map are visited.
+    key_order: KeyOrder,
+}
+
+/// What to do with a top-level value which fails to deserialize, set using
+/// [`Config::on_error`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Return the error. This is the default.
+    Fail,
+    /// Deserialize as though the key were absent from the querystring.
+    Skip,
+    /// Deserialize an empty value, such as `0`, `""` or `None`, in its place.
+    Default,
+}
+
+/// What to do with a key which appears more than once with a flat value, as
+/// in `color=red&color=blue`, set using [`Config::repeated_keys`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RepeatedKeys {
+    /// Collect the values into a sequence, as though the key were `color[]`.
+    /// This is the default.
+    AsSequence,
+    /// Return an error.
+    Error,
+}
+
+/// The order in which the keys at each level are visited when deserializing,
+/// set using [`Config::key_order`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// Sort the keys. This is the default.
+    Sorted,
+    /// Keep the keys in the order they first appear in the querystring.
+    Insertion,
+}
+
+/// Controls how flat values are handed over to the visitor.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Coercion {
+    /// Values are parsed into whichever primitive type is requested.
+    Parse,
+    /// Values are only ever visited as strings.
+    AllStr,
+    /// Like `Parse`, but integers also accept decimal and exponent notation,
+    /// truncating towards zero.
+    Lenient,
+    /// Like `Parse`, but values of unknown type are visited as the number or
+    /// boolean they look like, rather than as strings.
+    Natural,
 }
 
 pub const DEFAULT_CONFIG: Config = Config {
     max_depth: 5,
     strict: true,
+    coercion: Coercion::Parse,
+    enum_tag_key: None,
+    variant_lookup: None,
+    encoding_set: QS_ENCODE_SET,
+    strip_bom: false,
+    bracket_depth_limit: None,
+    serialize_depth_limit: 128,
+    array_limit: None,
+    key_limit: 1000,
+    max_value_count: 1000,
+    value_length_limit: 64 * 1024,
+    key_length_limit: 256,
+    error_policy: ErrorPolicy::Fail,
+    strict_utf8: None,
+    array_format: ArrayFormat::Indexed,
+    leading_question_mark: false,
+    percent_decode: true,
+    plus_as_space: true,
+    empty_string_as_none: true,
+    bool_strings: (&["true", "1", "yes"], &["false", "0", "no"]),
+    repeated_keys: RepeatedKeys::AsSequence,
+    pair_separator: b'&',
+    key_value_separator: b'=',
+    key_order: KeyOrder::Sorted,
 };
 
+/// Checks that `separator` can be used to separate pairs or keys and values.
+fn separator_byte(separator: char) -> u8 {
+    match separator {
+        '[' | ']' | '%' | '+' => panic!("{:?} cannot be used as a separator", separator),
+        c if c.is_ascii() => c as u8,
+        c => panic!("separator {:?} is not ASCII", c),
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         DEFAULT_CONFIG
@@ -103,19 +251,662 @@ impl Default for Config {
 impl Config {
     /// Create a new `Config` with the specified `max_depth` and `strict` mode.
     pub fn new(max_depth: usize, strict: bool) -> Self {
-        Self { max_depth, strict }
+        Self {
+            max_depth,
+            strict,
+            ..DEFAULT_CONFIG
+        }
+    }
+
+    /// Set the maximum depth of nested keys which are parsed, beyond which
+    /// any remaining brackets are parsed as part of the key. Default is 5.
+    ///
+    /// ```
+    /// use serde_qs::Config;
+    /// use std::collections::HashMap;
+    ///
+    /// let config = Config::default().max_depth(0);
+    /// let map: HashMap<String, String> = config.deserialize_str("a[b]=1").unwrap();
+    /// assert_eq!(map["a[b]"], "1");
+    /// ```
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Set whether strict mode is used. Default is `true`.
+    ///
+    /// See the [crate documentation](crate#strict-vs-non-strict-modes) for
+    /// the differences between the modes.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Set whether a `?` is written before the first pair when serializing,
+    /// e.g. for appending the output directly to a path. Default is `false`.
+    ///
+    /// A leading `?` is always ignored when deserializing.
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// # extern crate serde_qs;
+    /// #[derive(Serialize)]
+    /// struct Query {
+    ///     page: u32,
+    /// }
+    ///
+    /// # fn main(){
+    /// let config = serde_qs::Config::default().include_leading_question_mark(true);
+    /// let url = format!("/search{}", config.serialize_string(&Query { page: 2 }).unwrap());
+    /// assert_eq!(url, "/search?page=2");
+    /// # }
+    /// ```
+    pub fn include_leading_question_mark(mut self, include: bool) -> Self {
+        self.leading_question_mark = include;
+        self
+    }
+
+    /// Set whether keys and values are percent-decoded, and `+` replaced with
+    /// a space, when parsing. Default is `true`.
+    ///
+    /// Disabling this is useful when the querystring has already been decoded,
+    /// e.g. by a reverse proxy, in which case the input must be valid UTF-8.
+    /// Note that separators and brackets within keys or values cannot be
+    /// told apart from those of the querystring once decoded.
+    pub fn percent_decode(mut self, percent_decode: bool) -> Self {
+        self.percent_decode = percent_decode;
+        self
+    }
+
+    /// Set whether `+` in keys and values is decoded as a space, as specified
+    /// for `application/x-www-form-urlencoded`. Default is `true`.
+    ///
+    /// When disabled, `+` is kept literally, and a space must be encoded as
+    /// `%20`. This has no effect when [`Config::percent_decode`] is disabled.
+    ///
+    /// ```
+    /// use serde_qs::Config;
+    /// use std::collections::HashMap;
+    ///
+    /// let map: HashMap<String, String> = serde_qs::from_str("a+b=1+2").unwrap();
+    /// assert_eq!(map["a b"], "1 2");
+    ///
+    /// let config = Config::default().plus_as_space(false);
+    /// let map: HashMap<String, String> = config.deserialize_str("a+b=1+2%202").unwrap();
+    /// assert_eq!(map["a+b"], "1+2 2");
+    /// ```
+    pub fn plus_as_space(mut self, plus_as_space: bool) -> Self {
+        self.plus_as_space = plus_as_space;
+        self
+    }
+
+    /// Set whether a key with an empty value, such as `name=`, deserializes
+    /// as `None` for an `Option` field. Default is `true`.
+    ///
+    /// When disabled, the empty value is deserialized as the inner type
+    /// instead, e.g. `Some("")` for an `Option<String>`. Absent keys are
+    /// always `None`.
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// # extern crate serde_qs;
+    /// use serde_qs::Config;
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq)]
+    /// struct Query {
+    ///     name: Option<String>,
+    /// }
+    ///
+    /// # fn main(){
+    /// let query: Query = serde_qs::from_str("name=").unwrap();
+    /// assert_eq!(query.name, None);
+    ///
+    /// let config = Config::default().empty_string_as_none(false);
+    /// let query: Query = config.deserialize_str("name=").unwrap();
+    /// assert_eq!(query.name, Some(String::new()));
+    /// # }
+    /// ```
+    pub fn empty_string_as_none(mut self, empty_string_as_none: bool) -> Self {
+        self.empty_string_as_none = empty_string_as_none;
+        self
+    }
+
+    /// Set the values accepted for `bool` fields, which are compared
+    /// exactly. Any other value is an error. Default is `true`, `1` or `yes`
+    /// for `true`, and `false`, `0` or `no` for `false`.
+    ///
+    /// ```
+    /// use serde_qs::Config;
+    /// use std::collections::HashMap;
+    ///
+    /// let map: HashMap<String, bool> = serde_qs::from_str("a=true&b=0&c=yes").unwrap();
+    /// assert_eq!((map["a"], map["b"], map["c"]), (true, false, true));
+    ///
+    /// let config = Config::default().bool_strings(&["on"], &["off"]);
+    /// let map: HashMap<String, bool> = config.deserialize_str("a=on&b=off").unwrap();
+    /// assert_eq!((map["a"], map["b"]), (true, false));
+    /// assert!(config.deserialize_str::<HashMap<String, bool>>("a=true").is_err());
+    /// ```
+    pub fn bool_strings(
+        mut self,
+        true_strings: &'static [&'static str],
+        false_strings: &'static [&'static str],
+    ) -> Self {
+        self.bool_strings = (true_strings, false_strings);
+        self
+    }
+
+    /// Set what to do with a key which appears more than once, as in
+    /// `color=red&color=blue`. Default is [`RepeatedKeys::AsSequence`].
+    ///
+    /// The values are only collected when each of them is flat, so keys such
+    /// as `a[b]` may still not be repeated. The number of values is bounded
+    /// by [`Config::array_limit`], if set.
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// # extern crate serde_qs;
+    /// use serde_qs::{Config, RepeatedKeys};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq)]
+    /// struct Query {
+    ///     color: Vec<String>,
+    /// }
+    ///
+    /// # fn main(){
+    /// let query: Query = serde_qs::from_str("color=red&color=blue").unwrap();
+    /// assert_eq!(query.color, ["red", "blue"]);
+    ///
+    /// let config = Config::default().repeated_keys(RepeatedKeys::Error);
+    /// assert!(config.deserialize_str::<Query>("color=red&color=blue").is_err());
+    /// # }
+    /// ```
+    pub fn repeated_keys(mut self, repeated_keys: RepeatedKeys) -> Self {
+        self.repeated_keys = repeated_keys;
+        self
+    }
+
+    /// Set the order in which the keys at each level are visited when
+    /// deserializing. Default is [`KeyOrder::Sorted`].
+    ///
+    /// This matters for maps which keep the order of their entries, such as an
+    /// `IndexMap`. Sequences are always visited in index order, and
+    /// serializing follows the order of the value itself.
+    ///
+    /// ```
+    /// use indexmap::IndexMap;
+    /// use serde_qs::{Config, KeyOrder};
+    ///
+    /// let map: IndexMap<String, String> = serde_qs::from_str("b=2&a=1").unwrap();
+    /// assert_eq!(map.keys().collect::<Vec<_>>(), ["a", "b"]);
+    ///
+    /// let config = Config::default().key_order(KeyOrder::Insertion);
+    /// let map: IndexMap<String, String> = config.deserialize_str("b=2&a=1").unwrap();
+    /// assert_eq!(map.keys().collect::<Vec<_>>(), ["b", "a"]);
+    /// ```
+    pub fn key_order(mut self, key_order: KeyOrder) -> Self {
+        self.key_order = key_order;
+        self
+    }
+
+    /// Set the character separating pairs when parsing, such as `;` for
+    /// `a=1;b=2`. Default is `&`. Serializing always uses `&`.
+    ///
+    /// # Panics
+    ///
+    /// If `separator` is not ASCII, or is one of `[`, `]`, `%` or `+`. Parsing
+    /// returns an error if both separators are the same.
+    ///
+    /// ```
+    /// use serde_qs::Config;
+    /// use std::collections::HashMap;
+    ///
+    /// let config = Config::default().pair_separator(';').key_value_separator(':');
+    /// let map: HashMap<String, String> = config.deserialize_str("a:1;b:x=y&z").unwrap();
+    /// assert_eq!(map["a"], "1");
+    /// assert_eq!(map["b"], "x=y&z");
+    /// ```
+    pub fn pair_separator(mut self, separator: char) -> Self {
+        self.pair_separator = separator_byte(separator);
+        self
+    }
+
+    /// Set the character separating a key from its value when parsing, such
+    /// as `:` for `a:1`. Default is `=`. Serializing always uses `=`.
+    ///
+    /// # Panics
+    ///
+    /// If `separator` is not ASCII, or is one of `[`, `]`, `%` or `+`. Parsing
+    /// returns an error if both separators are the same.
+    pub fn key_value_separator(mut self, separator: char) -> Self {
+        self.key_value_separator = separator_byte(separator);
+        self
+    }
+
+    /// Whether percent-decoded values which are not valid UTF-8 are an error.
+    pub(crate) fn is_strict_utf8(&self) -> bool {
+        self.strict_utf8.unwrap_or(self.strict)
+    }
+
+    /// Read the variant of an enum from the value of `key`, rather than
+    /// expecting the variant name as the single key of the enum.
+    ///
+    /// The remaining keys at that level are used as the contents of the
+    /// variant. If `key` is absent, the usual representation is expected.
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// # extern crate serde_qs;
+    /// #[derive(Debug, Deserialize, PartialEq)]
+    /// enum Shape {
+    ///     Circle { radius: u8 },
+    ///     Square { side: u8 },
+    /// }
+    ///
+    /// # fn main(){
+    /// let config = serde_qs::Config::default().enum_tag_key("type");
+    /// assert_eq!(
+    ///     config.deserialize_str::<Shape>("type=Circle&radius=2").unwrap(),
+    ///     Shape::Circle { radius: 2 });
+    /// # }
+    /// ```
+    pub fn enum_tag_key(mut self, key: &'static str) -> Self {
+        self.enum_tag_key = Some(key);
+        self
+    }
+
+    /// Map the variant names found in the querystring to the names of enum
+    /// variants, e.g. to match them case-insensitively. Names for which
+    /// `lookup` returns `None` are used unchanged.
+    ///
+    /// `lookup` must be a function, or a closure which captures nothing, so
+    /// that `Config` remains `Copy`.
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// # extern crate serde_qs;
+    /// #[derive(Debug, Deserialize, PartialEq)]
+    /// enum Status {
+    ///     Active,
+    ///     Inactive,
+    /// }
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq)]
+    /// struct Query {
+    ///     status: Status,
+    /// }
+    ///
+    /// # fn main(){
+    /// let config = serde_qs::Config::default().variant_lookup(|name| {
+    ///     ["Active", "Inactive"]
+    ///         .iter()
+    ///         .copied()
+    ///         .find(|variant| variant.eq_ignore_ascii_case(name))
+    /// });
+    /// assert_eq!(
+    ///     config.deserialize_str::<Query>("status=inactive").unwrap(),
+    ///     Query { status: Status::Inactive });
+    /// # }
+    /// ```
+    pub fn variant_lookup(mut self, lookup: fn(&str) -> Option<&'static str>) -> Self {
+        self.variant_lookup = Some(lookup);
+        self
+    }
+
+    /// Applies `variant_lookup` to the variant name `name`.
+    fn variant_name<'a>(&self, name: Cow<'a, str>) -> Cow<'a, str> {
+        match self.variant_lookup.and_then(|lookup| lookup(&name)) {
+            Some(variant) => Cow::Borrowed(variant),
+            None => name,
+        }
+    }
+
+    /// Set the characters which are percent-encoded in serialized keys and
+    /// values. Spaces which are not encoded are written as `+`.
+    ///
+    /// The default is [`QS_ENCODE_SET`](crate::QS_ENCODE_SET), which can be
+    /// extended or relaxed as needed. Note that `[`, `]`, `&`, `=` and `+`
+    /// should remain encoded for the output to be parsed back correctly.
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// # extern crate serde_qs;
+    /// use serde_qs::{AsciiSet, QS_ENCODE_SET};
+    ///
+    /// const PATH_SET: &AsciiSet = &QS_ENCODE_SET.remove(b'/');
+    ///
+    /// #[derive(Serialize)]
+    /// struct Query {
+    ///     path: String,
+    /// }
+    ///
+    /// # fn main(){
+    /// let q = Query { path: "/a/b c".to_owned() };
+    /// let config = serde_qs::Config::default().encoding_set(PATH_SET);
+    /// assert_eq!(config.serialize_string(&q).unwrap(), "path=/a/b+c");
+    /// assert_eq!(serde_qs::to_string(&q).unwrap(), "path=%2Fa%2Fb+c");
+    /// # }
+    /// ```
+    pub fn encoding_set(mut self, set: &'static AsciiSet) -> Self {
+        self.encoding_set = set;
+        self
+    }
+
+    /// Ignore a UTF-8 byte order mark (`\xEF\xBB\xBF`) at the start of the
+    /// input, as prepended by some tools. Default is `false`.
+    pub fn strip_bom(mut self, strip_bom: bool) -> Self {
+        self.strip_bom = strip_bom;
+        self
+    }
+
+    /// Reject keys with more than `limit` bracketed segments, e.g. `a[b][c]`
+    /// has two. By default there is no limit.
+    ///
+    /// This is independent of `max_depth`, which limits how deeply values
+    /// are nested and parses any remaining segments as part of the key.
+    /// Instead, this limit bounds the length of the keys themselves, and
+    /// input exceeding it is an error.
+    ///
+    /// ```
+    /// use serde_qs::Config;
+    /// use std::collections::HashMap;
+    ///
+    /// let config = Config::default().bracket_depth_limit(2);
+    /// let map: HashMap<String, HashMap<String, HashMap<String, String>>> =
+    ///     config.deserialize_str("a[b][c]=1").unwrap();
+    /// assert_eq!(map["a"]["b"]["c"], "1");
+    ///
+    /// assert!(config
+    ///     .deserialize_str::<HashMap<String, String>>("a[b][c][d]=1")
+    ///     .is_err());
+    /// ```
+    pub fn bracket_depth_limit(mut self, limit: usize) -> Self {
+        self.bracket_depth_limit = Some(limit);
+        self
+    }
+
+    /// Reject values nested more than `limit` levels deep when serializing,
+    /// which guards against runaway recursive types. Default is 128.
+    ///
+    /// This is independent of `max_depth`, which only applies to parsing.
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// # extern crate serde_qs;
+    /// #[derive(Serialize)]
+    /// struct Tree {
+    ///     value: u8,
+    ///     child: Option<Box<Tree>>,
+    /// }
+    ///
+    /// # fn main(){
+    /// let tree = Tree {
+    ///     value: 1,
+    ///     child: Some(Box::new(Tree { value: 2, child: None })),
+    /// };
+    ///
+    /// let config = serde_qs::Config::default().serialize_depth_limit(1);
+    /// assert_eq!(
+    ///     config.serialize_string(&tree).unwrap(),
+    ///     "value=1&child[value]=2");
+    ///
+    /// let config = serde_qs::Config::default().serialize_depth_limit(0);
+    /// assert!(config.serialize_string(&tree).is_err());
+    /// # }
+    /// ```
+    pub fn serialize_depth_limit(mut self, limit: usize) -> Self {
+        self.serialize_depth_limit = limit;
+        self
+    }
+
+    /// Reject sequence indices greater than `limit`, so e.g. `a[256]=1` is an
+    /// error for a limit of 255. Unindexed elements such as `a[]=1` count
+    /// towards the limit in order. By default there is no limit.
+    ///
+    /// ```
+    /// use serde_qs::Config;
+    ///
+    /// let config = Config::default().array_limit(2);
+    /// let ids: Vec<u8> = config.deserialize_str("[0]=1&[2]=3").unwrap();
+    /// assert_eq!(ids, [1, 3]);
+    ///
+    /// let err = config.deserialize_str::<Vec<u8>>("[3]=1").unwrap_err();
+    /// assert!(err.to_string().contains("array index 3 exceeds configured limit of 2"));
+    /// ```
+    pub fn array_limit(mut self, limit: usize) -> Self {
+        self.array_limit = Some(limit);
+        self
+    }
+
+    /// Reject querystrings with more than `limit` distinct top-level keys,
+    /// to bound the memory used by untrusted input. Default is 1000.
+    pub fn key_limit(mut self, limit: usize) -> Self {
+        self.key_limit = limit;
+        self
+    }
+
+    /// Reject querystrings with more than `limit` values in total, counting
+    /// every element of every sequence, to bound the memory used by untrusted
+    /// input. Unlike `key_limit` and `array_limit`, this also bounds e.g.
+    /// `a[]=1&a[]=2&...`. Default is 1000.
+    ///
+    /// ```
+    /// use serde_qs::Config;
+    /// use std::collections::HashMap;
+    ///
+    /// let config = Config::default().max_value_count(3);
+    /// let map: HashMap<String, Vec<u8>> = config.deserialize_str("a[]=1&a[]=2&b=3").unwrap();
+    /// assert_eq!(map["a"], [1, 2]);
+    ///
+    /// let err = config
+    ///     .deserialize_str::<HashMap<String, Vec<u8>>>("a[]=1&a[]=2&a[]=3&a[]=4")
+    ///     .unwrap_err();
+    /// assert!(err.to_string().contains("exceeded the limit of 3 values"));
+    /// ```
+    pub fn max_value_count(mut self, limit: usize) -> Self {
+        self.max_value_count = limit;
+        self
+    }
+
+    /// Reject querystrings containing a value longer than `limit` bytes, as
+    /// measured before percent-decoding. Default is 64 KiB.
+    ///
+    /// ```
+    /// use serde_qs::Config;
+    /// use std::collections::HashMap;
+    ///
+    /// let config = Config::default().value_length_limit(4);
+    /// let map: HashMap<String, String> = config.deserialize_str("a=abcd").unwrap();
+    /// assert_eq!(map["a"], "abcd");
+    ///
+    /// assert!(config
+    ///     .deserialize_str::<HashMap<String, String>>("a=abcde")
+    ///     .is_err());
+    /// ```
+    pub fn value_length_limit(mut self, limit: usize) -> Self {
+        self.value_length_limit = limit;
+        self
+    }
+
+    /// Reject querystrings containing a key longer than `limit` bytes,
+    /// including any brackets and measured before percent-decoding. Default
+    /// is 256.
+    pub fn key_length_limit(mut self, limit: usize) -> Self {
+        self.key_length_limit = limit;
+        self
+    }
+
+    /// Choose how to handle a top-level value which fails to deserialize,
+    /// e.g. a number field receiving a string. Default is
+    /// [`ErrorPolicy::Fail`].
+    ///
+    /// With [`ErrorPolicy::Skip`] the key is ignored, so the field must be
+    /// optional or have a `#[serde(default)]`. With [`ErrorPolicy::Default`]
+    /// an empty value is used instead, which matches `Default::default()`
+    /// for primitives, strings, collections and `Option`. Errors in nested
+    /// values affect the whole top-level value, and errors in the querystring
+    /// syntax are always returned.
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// # extern crate serde_qs;
+    /// use serde_qs::{Config, ErrorPolicy};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq)]
+    /// struct Query {
+    ///     page: Option<u32>,
+    ///     limit: u32,
+    /// }
+    ///
+    /// # fn main(){
+    /// let input = "page=first&limit=ten";
+    /// assert!(serde_qs::from_str::<Query>(input).is_err());
+    ///
+    /// let config = Config::default().on_error(ErrorPolicy::Default);
+    /// assert_eq!(
+    ///     config.deserialize_str::<Query>(input).unwrap(),
+    ///     Query { page: None, limit: 0 });
+    ///
+    /// let config = Config::default().on_error(ErrorPolicy::Skip);
+    /// assert_eq!(
+    ///     config.deserialize_str::<Query>("page=first&limit=10").unwrap(),
+    ///     Query { page: None, limit: 10 });
+    /// # }
+    /// ```
+    pub fn on_error(mut self, policy: ErrorPolicy) -> Self {
+        self.error_policy = policy;
+        self
     }
 
-    /// Get maximum depth parameter.
-    fn max_depth(&self) -> usize {
-        self.max_depth
+    /// Choose whether percent-encoded sequences which do not decode to valid
+    /// UTF-8 are an error, or are replaced with `U+FFFD`, independently of
+    /// how encoded brackets are handled. By default this follows `strict`.
+    ///
+    /// ```
+    /// use serde_qs::Config;
+    /// use std::collections::HashMap;
+    ///
+    /// let config = Config::new(5, false);
+    /// let map: HashMap<String, String> = config.deserialize_str("a=%FF").unwrap();
+    /// assert_eq!(map["a"], "\u{FFFD}");
+    ///
+    /// assert!(config
+    ///     .strict_utf8(true)
+    ///     .deserialize_str::<HashMap<String, String>>("a=%FF")
+    ///     .is_err());
+    /// ```
+    pub fn strict_utf8(mut self, strict_utf8: bool) -> Self {
+        self.strict_utf8 = Some(strict_utf8);
+        self
+    }
+
+    /// Set how the elements of sequences are keyed when serializing. Default
+    /// is [`ArrayFormat::Indexed`].
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// # extern crate serde_qs;
+    /// use serde_qs::{ArrayFormat, Config};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Query {
+    ///     ids: Vec<u8>,
+    /// }
+    ///
+    /// # fn main(){
+    /// let q = Query { ids: vec![1, 2] };
+    /// let config = Config::default();
+    /// assert_eq!(config.serialize_string(&q).unwrap(), "ids[0]=1&ids[1]=2");
+    /// let config = config.array_format(ArrayFormat::Brackets);
+    /// assert_eq!(config.serialize_string(&q).unwrap(), "ids[]=1&ids[]=2");
+    /// let config = config.array_format(ArrayFormat::Repeat);
+    /// assert_eq!(config.serialize_string(&q).unwrap(), "ids=1&ids=2");
+    /// # }
+    /// ```
+    pub fn array_format(mut self, format: ArrayFormat) -> Self {
+        self.array_format = format;
+        self
     }
 }
 
 impl Config {
     /// Deserializes a querystring from a `&[u8]` using this `Config`.
     pub fn deserialize_bytes<'de, T: de::Deserialize<'de>>(&self, input: &'de [u8]) -> Result<T> {
-        T::deserialize(QsDeserializer::with_config(self, input)?)
+        match self.error_policy {
+            ErrorPolicy::Fail => T::deserialize(QsDeserializer::with_config(self, input)?),
+            policy => self.deserialize_recovering(
+                QsDeserializer::with_config(self, input)?,
+                policy,
+                |_| (),
+            ),
+        }
+    }
+
+    /// Deserializes already decoded `(key, value)` pairs using this `Config`.
+    ///
+    /// See [`from_pairs`].
+    pub fn deserialize_pairs<T, I, K, V>(&self, pairs: I) -> Result<T>
+    where
+        T: de::DeserializeOwned,
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let pairs: Vec<_> = pairs.into_iter().collect();
+        let deserializer = parse::pairs_deserializer(&pairs, *self)?;
+        match self.error_policy {
+            ErrorPolicy::Fail => T::deserialize(deserializer),
+            policy => self.deserialize_recovering(deserializer, policy, |_| ()),
+        }
+    }
+
+    /// Deserializes the map of `deserializer`, retrying whenever the value of
+    /// a new top-level key fails to deserialize with that key skipped or
+    /// defaulted according to `policy`. Each such error is passed to
+    /// `on_error`.
+    ///
+    /// The input is only parsed once: each attempt works on a copy of the
+    /// parsed map.
+    fn deserialize_recovering<'de, T: de::Deserialize<'de>>(
+        &self,
+        deserializer: QsDeserializer<'de>,
+        policy: ErrorPolicy,
+        mut on_error: impl FnMut(Error),
+    ) -> Result<T> {
+        let mut map: IndexMap<_, _> = deserializer.iter.collect();
+        let mut defaulted = BTreeSet::new();
+        loop {
+            let failed = RefCell::new(None);
+            let deserializer =
+                recover::RecoveringDeserializer::new(map.clone(), *self, &defaulted, &failed);
+            let e = match T::deserialize(deserializer) {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+            // Stop once an error can no longer be attributed to a new key,
+            // e.g. a missing field.
+            let recovered = match failed.into_inner() {
+                Some(key) if policy == ErrorPolicy::Skip => {
+                    map.shift_remove(key.as_str()).is_some()
+                }
+                Some(key) => defaulted.insert(key),
+                None => false,
+            };
+            if !recovered {
+                return Err(e);
+            }
+            on_error(e);
+        }
     }
 
     // pub fn deserialize_bytes_sloppy<T: de::DeserializeOwned>(&self, input: &[u8])
@@ -133,63 +924,596 @@ impl Config {
     }
 }
 
-/// Deserializes a querystring from a `&[u8]`.
+/// Deserializes a querystring from a `&[u8]`.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// # extern crate serde_qs;
+/// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+/// struct Query {
+///     name: String,
+///     age: u8,
+///     occupation: String,
+/// }
+///
+/// # fn main(){
+/// let q =  Query {
+///     name: "Alice".to_owned(),
+///     age: 24,
+///     occupation: "Student".to_owned(),
+/// };
+///
+/// assert_eq!(
+///     serde_qs::from_bytes::<Query>(
+///         "name=Alice&age=24&occupation=Student".as_bytes()
+///     ).unwrap(), q);
+/// # }
+/// ```
+pub fn from_bytes<'de, T: de::Deserialize<'de>>(input: &'de [u8]) -> Result<T> {
+    Config::default().deserialize_bytes(input)
+}
+
+/// Deserializes an already percent-decoded querystring from a `&[u8]`.
+///
+/// No percent-decoding takes place, and `+` is not replaced with a space. This
+/// is equivalent to using `Config::default().percent_decode(false)`.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// # extern crate serde_qs;
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct Query {
+///     discount: String,
+///     sum: String,
+/// }
+///
+/// # fn main(){
+/// assert_eq!(
+///     serde_qs::from_bytes_skip_encoding::<Query>(b"discount=50%&sum=1+1").unwrap(),
+///     Query { discount: "50%".to_owned(), sum: "1+1".to_owned() });
+/// # }
+/// ```
+pub fn from_bytes_skip_encoding<'de, T: de::Deserialize<'de>>(input: &'de [u8]) -> Result<T> {
+    Config::default()
+        .percent_decode(false)
+        .deserialize_bytes(input)
+}
+
+/// Deserializes a querystring from a `&[u8]` using the given `Config`.
+///
+/// This is equivalent to [`Config::deserialize_bytes`].
+pub fn from_bytes_with_config<'de, T: de::Deserialize<'de>>(
+    input: &'de [u8],
+    config: &Config,
+) -> Result<T> {
+    config.deserialize_bytes(input)
+}
+
+/// Deserializes a querystring read from `reader`.
+///
+/// The whole input is read before it is parsed.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// # extern crate serde_qs;
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct Query {
+///     name: String,
+///     age: u8,
+/// }
+///
+/// # fn main(){
+/// let body: &[u8] = b"name=Alice&age=24";
+/// assert_eq!(
+///     serde_qs::from_reader::<Query, _>(body).unwrap(),
+///     Query { name: "Alice".to_owned(), age: 24 });
+/// # }
+/// ```
+pub fn from_reader<T, R>(reader: R) -> Result<T>
+where
+    T: de::DeserializeOwned,
+    R: Read,
+{
+    from_reader_with_config(reader, &Config::default())
+}
+
+/// Deserializes a querystring read from `reader` using the given `Config`.
+///
+/// The whole input is read before it is parsed.
+pub fn from_reader_with_config<T, R>(mut reader: R, config: &Config) -> Result<T>
+where
+    T: de::DeserializeOwned,
+    R: Read,
+{
+    let mut buffer = Vec::new();
+    reader.read_to_end(&mut buffer)?;
+    config.deserialize_bytes(&buffer)
+}
+
+/// Deserializes a querystring read from an asynchronous `reader`, such as a
+/// request body.
+///
+/// The whole input is read before it is parsed, as with [`from_reader`].
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// # extern crate serde_qs;
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct Query {
+///     name: String,
+///     age: u8,
+/// }
+///
+/// # fn main(){
+/// # futures::executor::block_on(async {
+/// let body: &[u8] = b"name=Alice&age=24";
+/// assert_eq!(
+///     serde_qs::from_async_reader::<Query, _>(body).await.unwrap(),
+///     Query { name: "Alice".to_owned(), age: 24 });
+/// # });
+/// # }
+/// ```
+#[cfg(feature = "tokio")]
+pub async fn from_async_reader<T, R>(reader: R) -> Result<T>
+where
+    T: de::DeserializeOwned,
+    R: tokio::io::AsyncRead + Unpin,
+{
+    from_async_reader_with_config(reader, &Config::default()).await
+}
+
+/// Deserializes a querystring read from an asynchronous `reader` using the
+/// given `Config`.
+///
+/// The whole input is read before it is parsed.
+#[cfg(feature = "tokio")]
+pub async fn from_async_reader_with_config<T, R>(mut reader: R, config: &Config) -> Result<T>
+where
+    T: de::DeserializeOwned,
+    R: tokio::io::AsyncRead + Unpin,
+{
+    use tokio::io::AsyncReadExt;
+
+    let mut buffer = Vec::new();
+    reader.read_to_end(&mut buffer).await?;
+    config.deserialize_bytes(&buffer)
+}
+
+/// Deserializes already decoded `(key, value)` pairs, e.g. as returned by
+/// `url::form_urlencoded::parse`, without encoding them into a querystring.
+///
+/// Keys are in bracket notation as in a querystring, but are not
+/// percent-decoded, and neither are the values. The position of a parse error
+/// is the index of the offending pair.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// # extern crate serde_qs;
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct Query {
+///     name: String,
+///     tags: Vec<String>,
+/// }
+///
+/// # fn main(){
+/// let pairs = vec![("name", "A&B"), ("tags[]", "x=1"), ("tags[]", "y")];
+/// assert_eq!(
+///     serde_qs::from_pairs::<Query, _, _, _>(pairs).unwrap(),
+///     Query {
+///         name: "A&B".to_owned(),
+///         tags: vec!["x=1".to_owned(), "y".to_owned()],
+///     });
+/// # }
+/// ```
+pub fn from_pairs<T, I, K, V>(pairs: I) -> Result<T>
+where
+    T: de::DeserializeOwned,
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    Config::default().deserialize_pairs(pairs)
+}
+
+/// Deserializes a querystring from a `&[u8]`, ignoring a leading UTF-8 byte
+/// order mark.
+///
+/// This is equivalent to using `Config::default().strip_bom(true)`.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// # extern crate serde_qs;
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct Query {
+///     name: String,
+/// }
+///
+/// # fn main(){
+/// assert_eq!(
+///     serde_qs::from_bytes_with_bom_stripping::<Query>(b"\xEF\xBB\xBFname=Alice").unwrap(),
+///     Query { name: "Alice".to_owned() });
+/// # }
+/// ```
+pub fn from_bytes_with_bom_stripping<'de, T: de::Deserialize<'de>>(input: &'de [u8]) -> Result<T> {
+    Config::default().strip_bom(true).deserialize_bytes(input)
+}
+
+/// Deserializes a querystring from a `&str`.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// # extern crate serde_qs;
+/// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+/// struct Query {
+///     name: String,
+///     age: u8,
+///     occupation: String,
+/// }
+///
+/// # fn main(){
+/// let q =  Query {
+///     name: "Alice".to_owned(),
+///     age: 24,
+///     occupation: "Student".to_owned(),
+/// };
+///
+/// assert_eq!(
+///     serde_qs::from_str::<Query>("name=Alice&age=24&occupation=Student").unwrap(),
+///     q);
+/// # }
+/// ```
+pub fn from_str<'de, T: de::Deserialize<'de>>(input: &'de str) -> Result<T> {
+    from_bytes(input.as_bytes())
+}
+
+/// Deserializes a querystring from a `&str` using the given `Config`.
+///
+/// This is equivalent to [`Config::deserialize_str`].
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// # extern crate serde_qs;
+/// use serde_qs::Config;
+///
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct Query {
+///     a: u8,
+///     b: u8,
+/// }
+///
+/// # fn main(){
+/// let config = Config::default().pair_separator(';');
+/// assert_eq!(
+///     serde_qs::from_str_with_config::<Query>("a=1;b=2", &config).unwrap(),
+///     Query { a: 1, b: 2 });
+/// # }
+/// ```
+pub fn from_str_with_config<'de, T: de::Deserialize<'de>>(
+    input: &'de str,
+    config: &Config,
+) -> Result<T> {
+    config.deserialize_str(input)
+}
+
+/// Deserializes a querystring from a `&str` using a `DeserializeSeed`.
+///
+/// This allows passing context (e.g. a schema) into deserialization without
+/// resorting to global state.
+///
+/// ```
+/// # extern crate serde;
+/// # extern crate serde_qs;
+/// use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, Visitor};
+/// use std::fmt;
+///
+/// /// Counts the keys which start with the given prefix.
+/// struct CountPrefixed<'a>(&'a str);
+///
+/// impl<'de> DeserializeSeed<'de> for CountPrefixed<'_> {
+///     type Value = usize;
+///
+///     fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<usize, D::Error> {
+///         deserializer.deserialize_map(self)
+///     }
+/// }
+///
+/// impl<'de> Visitor<'de> for CountPrefixed<'_> {
+///     type Value = usize;
+///
+///     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+///         f.write_str("a map")
+///     }
+///
+///     fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<usize, A::Error> {
+///         let mut count = 0;
+///         while let Some(key) = map.next_key::<String>()? {
+///             map.next_value::<IgnoredAny>()?;
+///             if key.starts_with(self.0) {
+///                 count += 1;
+///             }
+///         }
+///         Ok(count)
+///     }
+/// }
+///
+/// # fn main(){
+/// assert_eq!(
+///     serde_qs::from_str_seeded("x_a=1&x_b=2&y=3", CountPrefixed("x_")).unwrap(),
+///     2);
+/// # }
+/// ```
+pub fn from_str_seeded<'de, S: de::DeserializeSeed<'de>>(
+    input: &'de str,
+    seed: S,
+) -> Result<S::Value> {
+    seed.deserialize(QsDeserializer::with_config(
+        &Config::default(),
+        input.as_bytes(),
+    )?)
+}
+
+/// Deserializes a querystring from a `&str`, returning an error if any
+/// percent-encoded sequence does not decode to valid UTF-8.
+///
+/// This is already the case with the default `Config`, but this function
+/// states the intent explicitly. See [`Config::strict_utf8`] to combine it
+/// with non-strict bracket handling.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// # extern crate serde_qs;
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct Query {
+///     name: String,
+/// }
+///
+/// # fn main(){
+/// assert_eq!(
+///     serde_qs::from_str_strict_utf8::<Query>("name=%C3%A9").unwrap(),
+///     Query { name: "é".to_owned() });
+///
+/// assert!(serde_qs::from_str_strict_utf8::<Query>("name=%C3").is_err());
+/// # }
+/// ```
+pub fn from_str_strict_utf8<'de, T: de::Deserialize<'de>>(input: &'de str) -> Result<T> {
+    Config::default().strict_utf8(true).deserialize_str(input)
+}
+
+/// Deserializes a querystring from a `&str`, visiting every value as a
+/// string.
+///
+/// No implicit parsing of primitives takes place, so a value such as `08`
+/// is never silently reinterpreted as a number. Fields which are not
+/// strings must convert the value themselves, for example using `FromStr`.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// # extern crate serde_qs;
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct Query {
+///     name: String,
+///     zip: String,
+/// }
+///
+/// #[derive(Debug, Deserialize)]
+/// struct Numeric {
+///     zip: u32,
+/// }
+///
+/// # fn main(){
+/// assert_eq!(
+///     serde_qs::from_str_all_str::<Query>("name=Alice&zip=08540").unwrap(),
+///     Query { name: "Alice".to_owned(), zip: "08540".to_owned() });
+///
+/// assert!(serde_qs::from_str_all_str::<Numeric>("zip=08540").is_err());
+/// # }
+/// ```
+pub fn from_str_all_str<'de, T: de::Deserialize<'de>>(input: &'de str) -> Result<T> {
+    Config {
+        coercion: Coercion::AllStr,
+        ..Config::default()
+    }
+    .deserialize_str(input)
+}
+
+/// Deserializes a querystring from a `&str`, taking any value missing from
+/// the querystring from `base`.
+///
+/// Maps and structs are merged key by key at every level of nesting, while
+/// any other value present in the querystring replaces the one in `base`
+/// entirely. This makes it possible to apply a partial querystring on top of
+/// existing values. `base` is taken as is, so it is not subject to the limits
+/// of the default `Config`.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// # extern crate serde_qs;
+/// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+/// struct Query {
+///     name: String,
+///     age: u8,
+///     occupation: String,
+/// }
+///
+/// # fn main(){
+/// let base = Query {
+///     name: "Alice".to_owned(),
+///     age: 24,
+///     occupation: "Student".to_owned(),
+/// };
+///
+/// assert_eq!(
+///     serde_qs::from_str_with_base("age=25", base).unwrap(),
+///     Query {
+///         name: "Alice".to_owned(),
+///         age: 25,
+///         occupation: "Student".to_owned(),
+///     });
+/// # }
+/// ```
+pub fn from_str_with_base<T>(input: &str, base: T) -> Result<T>
+where
+    T: ser::Serialize + de::DeserializeOwned,
+{
+    let config = Config::default();
+    let base = base::Captured::capture(&base)?;
+    let overlay = QsDeserializer::with_config(&config, input.as_bytes())?.iter;
+    T::deserialize(base::Merged::Both(
+        Level::Nested(overlay.collect()),
+        base,
+        config,
+    ))
+}
+
+/// Deserializes a querystring from a `&str`, inferring the type of values
+/// where the target type does not specify one.
+///
+/// Types which ask for a particular primitive, such as a `u32` field, are
+/// unaffected. Self-describing targets, such as `#[serde(untagged)]` enums,
+/// instead see integer-looking values as integers, float-looking values as
+/// floats, `true` and `false` as booleans and anything else as strings.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// # extern crate serde_qs;
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// #[serde(untagged)]
+/// enum Id {
+///     Number(u64),
+///     Name(String),
+/// }
+///
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct Query {
+///     a: Id,
+///     b: Id,
+/// }
+///
+/// # fn main(){
+/// assert_eq!(
+///     serde_qs::from_str_number_strings::<Query>("a=12&b=twelve").unwrap(),
+///     Query { a: Id::Number(12), b: Id::Name("twelve".to_owned()) });
+/// # }
+/// ```
+pub fn from_str_number_strings<'de, T: de::Deserialize<'de>>(input: &'de str) -> Result<T> {
+    Config {
+        coercion: Coercion::Natural,
+        ..Config::default()
+    }
+    .deserialize_str(input)
+}
+
+/// Deserializes a querystring from a `&str`, collecting the errors of every
+/// top-level field which fails to deserialize rather than stopping at the
+/// first one.
+///
+/// This is useful for validation, where all invalid fields should be
+/// reported at once. After a field fails, deserialization is retried using
+/// an empty value (such as `0` or `""`) in its place, so later fields are
+/// still checked. Errors in the querystring syntax itself are returned on
+/// their own.
 ///
 /// ```
 /// # #[macro_use]
 /// # extern crate serde_derive;
 /// # extern crate serde_qs;
-/// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+/// #[derive(Debug, Deserialize)]
 /// struct Query {
 ///     name: String,
 ///     age: u8,
-///     occupation: String,
+///     height: u16,
 /// }
 ///
 /// # fn main(){
-/// let q =  Query {
-///     name: "Alice".to_owned(),
-///     age: 24,
-///     occupation: "Student".to_owned(),
-/// };
-///
-/// assert_eq!(
-///     serde_qs::from_bytes::<Query>(
-///         "name=Alice&age=24&occupation=Student".as_bytes()
-///     ).unwrap(), q);
+/// let errors = serde_qs::from_str_collect_errors::<Query>("name=Alice&age=old&height=tall")
+///     .unwrap_err();
+/// assert_eq!(errors.len(), 2);
 /// # }
 /// ```
-pub fn from_bytes<'de, T: de::Deserialize<'de>>(input: &'de [u8]) -> Result<T> {
-    Config::default().deserialize_bytes(input)
+pub fn from_str_collect_errors<'de, T: de::Deserialize<'de>>(
+    input: &'de str,
+) -> std::result::Result<T, Vec<Error>> {
+    let mut errors = Vec::new();
+    let config = Config::default();
+    let result = QsDeserializer::with_config(&config, input.as_bytes()).and_then(|deserializer| {
+        config.deserialize_recovering(deserializer, ErrorPolicy::Default, |e| errors.push(e))
+    });
+    match result {
+        Ok(value) if errors.is_empty() => Ok(value),
+        Ok(_) => Err(errors),
+        Err(e) => {
+            errors.push(e);
+            Err(errors)
+        }
+    }
 }
 
-/// Deserializes a querystring from a `&str`.
+/// Parses a querystring into its top-level keys, without deserializing the
+/// values.
+///
+/// Each key maps to the [`Level`] tree parsed for it, which can be inspected
+/// directly, e.g. for logging or to dispatch on the keys present.
+///
+/// ```
+/// use serde_qs::Level;
+///
+/// let map = serde_qs::parse_top_level(b"a=1&b[c]=2").unwrap();
+/// assert!(matches!(map["a"], Level::Flat(ref v) if v == "1"));
+/// assert!(matches!(map["b"], Level::Nested(_)));
+/// assert_eq!(map["b"].to_string(), "c=2");
+/// ```
+pub fn parse_top_level(input: &[u8]) -> Result<HashMap<String, Level<'_>>> {
+    let deserializer = QsDeserializer::with_config(&Config::default(), input)?;
+    Ok(deserializer
+        .iter
+        .map(|(key, value)| (key.into_owned(), value))
+        .collect())
+}
+
+/// Deserializes a querystring from a `&str`, coercing numeric strings into
+/// integer fields where possible.
+///
+/// A value which is not a valid integer is parsed as a float and truncated
+/// towards zero, so `3.7` becomes `3` and `3e2` becomes `300`. Values which
+/// are out of range for the field are still an error. This is useful for
+/// querystrings produced by loosely-typed clients, such as JavaScript.
 ///
 /// ```
 /// # #[macro_use]
 /// # extern crate serde_derive;
 /// # extern crate serde_qs;
-/// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+/// #[derive(Debug, Deserialize, PartialEq)]
 /// struct Query {
-///     name: String,
-///     age: u8,
-///     occupation: String,
+///     page: u32,
+///     offset: i64,
 /// }
 ///
 /// # fn main(){
-/// let q =  Query {
-///     name: "Alice".to_owned(),
-///     age: 24,
-///     occupation: "Student".to_owned(),
-/// };
-///
 /// assert_eq!(
-///     serde_qs::from_str::<Query>("name=Alice&age=24&occupation=Student").unwrap(),
-///     q);
+///     serde_qs::from_str_lenient::<Query>("page=3.7&offset=-1e3").unwrap(),
+///     Query { page: 3, offset: -1000 });
+///
+/// assert!(serde_qs::from_str::<Query>("page=3.7&offset=-1e3").is_err());
 /// # }
 /// ```
-pub fn from_str<'de, T: de::Deserialize<'de>>(input: &'de str) -> Result<T> {
-    from_bytes(input.as_bytes())
+pub fn from_str_lenient<'de, T: de::Deserialize<'de>>(input: &'de str) -> Result<T> {
+    Config {
+        coercion: Coercion::Lenient,
+        ..Config::default()
+    }
+    .deserialize_str(input)
 }
 
 /// A deserializer for the querystring format.
@@ -198,29 +1522,117 @@ pub fn from_str<'de, T: de::Deserialize<'de>>(input: &'de str) -> Result<T> {
 pub struct QsDeserializer<'a> {
     iter: IntoIter<Cow<'a, str>, Level<'a>>,
     value: Option<Level<'a>>,
+    config: Config,
 }
 
-#[derive(Debug)]
-enum Level<'a> {
-    Nested(BTreeMap<Cow<'a, str>, Level<'a>>),
+/// The intermediate tree a querystring is parsed into before being
+/// deserialized.
+///
+/// Its `Display` implementation renders the tree back into a querystring
+/// fragment, so `a[b]=1&a[c][0]=2` displays the `a` sub-tree as
+/// `b=1&c[0]=2`.
+///
+/// With the `level-serde` feature, `Level` also implements `Serialize` and
+/// `Deserialize`, which is handy for inspecting the tree as e.g. JSON.
+///
+/// The parser may gain new kinds of level, so matches on `Level` need a
+/// wildcard arm.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum Level<'a> {
+    /// A map of string keys, e.g. `a[b]=1`, in the order set by
+    /// [`Config::key_order`].
+    Nested(IndexMap<Cow<'a, str>, Level<'a>>),
+    /// A sequence with explicit indices, e.g. `a[0]=1`.
     OrderedSeq(BTreeMap<usize, Level<'a>>),
+    /// A sequence without indices, e.g. `a[]=1`.
     Sequence(Vec<Level<'a>>),
+    /// A single (decoded) value.
     Flat(Cow<'a, str>),
+    /// A value which could not be parsed, along with the reason.
+    #[doc(hidden)]
     Invalid(String),
+    /// A placeholder for a value which has not been parsed yet.
+    #[doc(hidden)]
     Uninitialised,
 }
 
+impl Level<'_> {
+    /// Writes out each `(key, value)` pair of this level, with keys nested
+    /// under `prefix`.
+    fn fmt_pairs(
+        &self,
+        prefix: Option<&str>,
+        first: &mut bool,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        let child_key = |key: &str| match prefix {
+            Some(prefix) => format!("{}[{}]", prefix, encode(key)),
+            None => encode(key),
+        };
+        match *self {
+            Level::Nested(ref map) => {
+                for (key, value) in map {
+                    value.fmt_pairs(Some(&child_key(key)), first, f)?;
+                }
+            }
+            Level::OrderedSeq(ref map) => {
+                for (idx, value) in map {
+                    value.fmt_pairs(Some(&child_key(&idx.to_string())), first, f)?;
+                }
+            }
+            Level::Sequence(ref seq) => {
+                for (idx, value) in seq.iter().enumerate() {
+                    value.fmt_pairs(Some(&child_key(&idx.to_string())), first, f)?;
+                }
+            }
+            Level::Flat(ref value) => {
+                if !*first {
+                    f.write_str("&")?;
+                }
+                *first = false;
+                write!(f, "{}={}", prefix.unwrap_or_default(), encode(value))?;
+            }
+            // Nothing sensible can be written for these.
+            Level::Invalid(_) | Level::Uninitialised => {}
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Level<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Level::Flat(ref value) => f.write_str(&encode(value)),
+            _ => self.fmt_pairs(None, &mut true, f),
+        }
+    }
+}
+
+fn encode(input: &str) -> String {
+    percent_encode(input.as_bytes(), QS_ENCODE_SET)
+        .map(replace_space)
+        .collect()
+}
+
 impl<'a> QsDeserializer<'a> {
-    fn with_map(map: BTreeMap<Cow<'a, str>, Level<'a>>) -> Self {
+    fn with_map(map: IndexMap<Cow<'a, str>, Level<'a>>, config: Config) -> Self {
         QsDeserializer {
             iter: map.into_iter(),
             value: None,
+            config,
         }
     }
 
     /// Returns a new `QsDeserializer<'a>`.
     pub fn with_config(config: &Config, input: &'a [u8]) -> Result<Self> {
-        parse::Parser::new(input, config.max_depth(), config.strict).as_deserializer()
+        let input = match input.strip_prefix(b"\xEF\xBB\xBF") {
+            Some(stripped) if config.strip_bom => stripped,
+            _ => input,
+        };
+        // Allow passing e.g. the query of a URI, including its `?`, directly.
+        let input = input.strip_prefix(b"?").unwrap_or(input);
+        parse::Parser::new(input, *config).as_deserializer()
     }
 
     pub fn new(input: &'a [u8]) -> Result<Self> {
@@ -228,10 +1640,35 @@ impl<'a> QsDeserializer<'a> {
     }
 }
 
+/// Implements deserializing primitives, which cannot be represented by a
+/// whole querystring, as an error.
+macro_rules! top_level_primitives {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, _visitor: V) -> Result<V::Value>
+            where
+                V: de::Visitor<'de>,
+            {
+                Err(Error::top_level("primitive"))
+            }
+        )*
+    };
+}
+
 impl<'de> de::Deserializer<'de> for QsDeserializer<'de> {
     type Error = Error;
 
-    fn deserialize_any<V>(mut self, visitor: V) -> Result<V::Value>
+    /// Self-describing types, such as `serde_json::Value`, are visited as a
+    /// map of the top-level keys.
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    /// Only an empty querystring is a unit.
+    fn deserialize_unit<V>(mut self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
@@ -242,6 +1679,13 @@ impl<'de> de::Deserializer<'de> for QsDeserializer<'de> {
         Err(Error::top_level("primitive"))
     }
 
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
     fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
@@ -261,31 +1705,56 @@ impl<'de> de::Deserializer<'de> for QsDeserializer<'de> {
         self.deserialize_map(visitor)
     }
 
-    /// Throws an error.
+    /// Deserializes a sequence from top-level keys which are all indices,
+    /// e.g. `[0][a]=1&[1][a]=2`.
     ///
-    /// Sequences are not supported at the top level.
-    fn deserialize_seq<V>(self, _visitor: V) -> Result<V::Value>
+    /// Other sequences are not supported at the top level.
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        Err(Error::top_level("sequence"))
+        let mut seq = BTreeMap::new();
+        for (key, value) in self.iter {
+            match key.parse::<usize>() {
+                Ok(idx) => {
+                    seq.insert(idx, value);
+                }
+                Err(_) => return Err(Error::top_level("sequence")),
+            }
+        }
+        visitor.visit_seq(LevelSeq(seq.into_iter(), self.config))
     }
 
+    /// The wrapped value is deserialized from the whole querystring.
     fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_map(visitor)
+        visitor.visit_newtype_struct(self)
     }
 
-    /// Throws an error.
+    /// Deserializes a querystring holding exactly one pair as a `(key,
+    /// value)` 2-tuple.
     ///
-    /// Tuples are not supported at the top level.
-    fn deserialize_tuple<V>(self, _len: usize, _visitor: V) -> Result<V::Value>
+    /// Other tuples are not supported at the top level.
+    fn deserialize_tuple<V>(mut self, len: usize, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        Err(Error::top_level("tuple"))
+        if len != 2 {
+            return Err(Error::top_level("tuple"));
+        }
+        match (self.iter.next(), self.iter.len()) {
+            (Some((key, value)), 0) => visitor.visit_seq(PairSeq {
+                key: Some(key),
+                value: Some(value),
+                config: self.config,
+            }),
+            (first, rest) => Err(de::Error::custom(format!(
+                "expected exactly one pair to deserialize a tuple, found {}",
+                first.map_or(0, |_| rest + 1)
+            ))),
+        }
     }
 
     /// Throws an error.
@@ -312,31 +1781,63 @@ impl<'de> de::Deserializer<'de> for QsDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        if let Some(tag) = self.config.enum_tag_key {
+            let config = self.config;
+            let mut map: IndexMap<_, _> = self.iter.collect();
+            return match map.shift_remove(tag) {
+                Some(Level::Flat(variant)) => visitor.visit_enum(TaggedEnum {
+                    variant,
+                    content: Level::Nested(map),
+                    config,
+                }),
+                Some(_) => Err(de::Error::custom(format!(
+                    "the enum tag `{}` must be a single value",
+                    tag
+                ))),
+                None => visitor.visit_enum(QsDeserializer::with_map(map, config)),
+            };
+        }
         visitor.visit_enum(self)
     }
 
-    forward_to_deserialize_any! {
-        bool
-        u8
-        u16
-        u32
-        u64
-        i8
-        i16
-        i32
-        i64
-        f32
-        f64
-        char
-        str
-        string
-        unit
-        option
-        bytes
-        byte_buf
-        unit_struct
-        identifier
-        ignored_any
+    /// Visits every pair, so that the values are checked for errors.
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_map(self)
+    }
+
+    /// An empty querystring is `None`, and anything else `Some`.
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        if self.iter.len() == 0 {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    top_level_primitives! {
+        deserialize_bool
+        deserialize_u8
+        deserialize_u16
+        deserialize_u32
+        deserialize_u64
+        deserialize_i8
+        deserialize_i16
+        deserialize_i32
+        deserialize_i64
+        deserialize_f32
+        deserialize_f64
+        deserialize_char
+        deserialize_str
+        deserialize_string
+        deserialize_bytes
+        deserialize_byte_buf
+        deserialize_identifier
     }
 }
 
@@ -350,7 +1851,7 @@ impl<'de> de::MapAccess<'de> for QsDeserializer<'de> {
         if let Some((key, value)) = self.iter.next() {
             self.value = Some(value);
             let has_bracket = key.contains('[');
-            seed.deserialize(ParsableStringDeserializer(key))
+            seed.deserialize(ParsableStringDeserializer(key, self.config))
                 .map(Some)
                 .map_err(|e| {
                     if has_bracket {
@@ -371,7 +1872,7 @@ impl<'de> de::MapAccess<'de> for QsDeserializer<'de> {
         V: de::DeserializeSeed<'de>,
     {
         if let Some(v) = self.value.take() {
-            seed.deserialize(LevelDeserializer(v))
+            seed.deserialize(LevelDeserializer(v, self.config))
         } else {
             Err(de::Error::custom(
                 "Somehow the map was empty after a non-empty key was returned",
@@ -390,7 +1891,13 @@ impl<'de> de::EnumAccess<'de> for QsDeserializer<'de> {
     {
         if let Some((key, value)) = self.iter.next() {
             self.value = Some(value);
-            Ok((seed.deserialize(ParsableStringDeserializer(key))?, self))
+            Ok((
+                seed.deserialize(ParsableStringDeserializer(
+                    self.config.variant_name(key),
+                    self.config,
+                ))?,
+                self,
+            ))
         } else {
             Err(de::Error::custom("No more values"))
         }
@@ -408,7 +1915,7 @@ impl<'de> de::VariantAccess<'de> for QsDeserializer<'de> {
         T: de::DeserializeSeed<'de>,
     {
         if let Some(value) = self.value {
-            seed.deserialize(LevelDeserializer(value))
+            seed.deserialize(LevelDeserializer(value, self.config))
         } else {
             Err(de::Error::custom("no value to deserialize"))
         }
@@ -418,7 +1925,7 @@ impl<'de> de::VariantAccess<'de> for QsDeserializer<'de> {
         V: de::Visitor<'de>,
     {
         if let Some(value) = self.value {
-            de::Deserializer::deserialize_seq(LevelDeserializer(value), visitor)
+            de::Deserializer::deserialize_seq(LevelDeserializer(value, self.config), visitor)
         } else {
             Err(de::Error::custom("no value to deserialize"))
         }
@@ -428,13 +1935,67 @@ impl<'de> de::VariantAccess<'de> for QsDeserializer<'de> {
         V: de::Visitor<'de>,
     {
         if let Some(value) = self.value {
-            de::Deserializer::deserialize_map(LevelDeserializer(value), visitor)
+            de::Deserializer::deserialize_map(LevelDeserializer(value, self.config), visitor)
         } else {
             Err(de::Error::custom("no value to deserialize"))
         }
     }
 }
 
+/// Sequence access for a single `(key, value)` pair.
+struct PairSeq<'a> {
+    key: Option<Cow<'a, str>>,
+    value: Option<Level<'a>>,
+    config: Config,
+}
+
+impl<'de> de::SeqAccess<'de> for PairSeq<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        if let Some(key) = self.key.take() {
+            seed.deserialize(ParsableStringDeserializer(key, self.config))
+                .map(Some)
+        } else if let Some(value) = self.value.take() {
+            seed.deserialize(LevelDeserializer(value, self.config))
+                .map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.key.iter().count() + self.value.iter().count())
+    }
+}
+
+/// Enum access for an enum whose variant is named by
+/// `Config::enum_tag_key`.
+struct TaggedEnum<'a> {
+    variant: Cow<'a, str>,
+    content: Level<'a>,
+    config: Config,
+}
+
+impl<'de> de::EnumAccess<'de> for TaggedEnum<'de> {
+    type Error = Error;
+    type Variant = LevelDeserializer<'de>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(ParsableStringDeserializer(
+            self.config.variant_name(self.variant),
+            self.config,
+        ))?;
+        Ok((variant, LevelDeserializer(self.content, self.config)))
+    }
+}
+
 impl<'de> de::EnumAccess<'de> for LevelDeserializer<'de> {
     type Error = Error;
     type Variant = Self;
@@ -445,13 +2006,16 @@ impl<'de> de::EnumAccess<'de> for LevelDeserializer<'de> {
     {
         match self.0 {
             Level::Flat(x) => Ok((
-                seed.deserialize(ParsableStringDeserializer(x))?,
-                LevelDeserializer(Level::Invalid(
-                    "this value can only \
+                seed.deserialize(ParsableStringDeserializer(self.1.variant_name(x), self.1))?,
+                LevelDeserializer(
+                    Level::Invalid(
+                        "this value can only \
                      deserialize to a \
                      UnitVariant"
-                        .to_string(),
-                )),
+                            .to_string(),
+                    ),
+                    self.1,
+                ),
             )),
             _ => Err(de::Error::custom(
                 "this value can only deserialize to a \
@@ -487,44 +2051,40 @@ impl<'de> de::VariantAccess<'de> for LevelDeserializer<'de> {
     }
 }
 
-struct LevelSeq<'a, I: Iterator<Item = Level<'a>>>(I);
+/// Gives access to the elements of a sequence, along with their index so
+/// that errors can point at the offending element.
+struct LevelSeq<'a, I: Iterator<Item = (usize, Level<'a>)>>(I, Config);
 
-impl<'de, I: Iterator<Item = Level<'de>>> de::SeqAccess<'de> for LevelSeq<'de, I> {
+impl<'de, I: Iterator<Item = (usize, Level<'de>)>> de::SeqAccess<'de> for LevelSeq<'de, I> {
     type Error = Error;
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
     where
         T: de::DeserializeSeed<'de>,
     {
-        if let Some(v) = self.0.next() {
-            seed.deserialize(LevelDeserializer(v)).map(Some)
+        if let Some((idx, v)) = self.0.next() {
+            seed.deserialize(LevelDeserializer(v, self.1))
+                .map(Some)
+                .map_err(|e| Error::Custom(format!("{} (at sequence index {})", e, idx)))
         } else {
             Ok(None)
         }
     }
 }
 
-struct LevelDeserializer<'a>(Level<'a>);
+struct LevelDeserializer<'a>(Level<'a>, Config);
 
 macro_rules! deserialize_primitive {
-    ($ty:ident, $method:ident, $visit_method:ident) => {
+    ($method:ident) => {
         fn $method<V>(self, visitor: V) -> Result<V::Value>
         where
             V: de::Visitor<'de>,
         {
             match self.0 {
-                Level::Nested(_) => Err(de::Error::custom(format!(
-                    "Expected: {:?}, got a Map",
-                    stringify!($ty)
-                ))),
-                Level::OrderedSeq(_) => Err(de::Error::custom(format!(
-                    "Expected: {:?}, got an OrderedSequence",
-                    stringify!($ty)
-                ))),
-                Level::Sequence(_) => Err(de::Error::custom(format!(
-                    "Expected: {:?}, got a Sequence",
-                    stringify!($ty)
-                ))),
-                Level::Flat(x) => ParsableStringDeserializer(x).$method(visitor),
+                Level::Nested(_) => Err(de::Error::invalid_type(de::Unexpected::Map, &visitor)),
+                Level::OrderedSeq(_) | Level::Sequence(_) => {
+                    Err(de::Error::invalid_type(de::Unexpected::Seq, &visitor))
+                }
+                Level::Flat(x) => ParsableStringDeserializer(x, self.1).$method(visitor),
                 Level::Invalid(e) => Err(de::Error::custom(e)),
                 Level::Uninitialised => Err(de::Error::custom(
                     "attempted to deserialize unitialised value",
@@ -537,11 +2097,12 @@ macro_rules! deserialize_primitive {
 impl<'a> LevelDeserializer<'a> {
     fn into_deserializer(self) -> Result<QsDeserializer<'a>> {
         match self.0 {
-            Level::Nested(map) => Ok(QsDeserializer::with_map(map)),
+            Level::Nested(map) => Ok(QsDeserializer::with_map(map, self.1)),
             Level::OrderedSeq(map) => Ok(QsDeserializer::with_map(
                 map.into_iter()
                     .map(|(k, v)| (Cow::Owned(k.to_string()), v))
                     .collect(),
+                self.1,
             )),
             Level::Invalid(e) => Err(de::Error::custom(e)),
             l => Err(de::Error::custom(format!(
@@ -562,8 +2123,11 @@ impl<'de> de::Deserializer<'de> for LevelDeserializer<'de> {
     {
         match self.0 {
             Level::Nested(_) => self.into_deserializer()?.deserialize_map(visitor),
-            Level::OrderedSeq(map) => visitor.visit_seq(LevelSeq(map.into_values())),
-            Level::Sequence(seq) => visitor.visit_seq(LevelSeq(seq.into_iter())),
+            Level::OrderedSeq(map) => visitor.visit_seq(LevelSeq(map.into_iter(), self.1)),
+            Level::Sequence(seq) => {
+                visitor.visit_seq(LevelSeq(seq.into_iter().enumerate(), self.1))
+            }
+            Level::Flat(x) if self.1.coercion == Coercion::Natural => visit_natural(x, visitor),
             Level::Flat(x) => match x {
                 Cow::Owned(s) => visitor.visit_string(s),
                 Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
@@ -581,7 +2145,9 @@ impl<'de> de::Deserializer<'de> for LevelDeserializer<'de> {
         V: de::Visitor<'de>,
     {
         match self.0 {
-            Level::Flat(ref x) if x == "" => visitor.visit_none(),
+            Level::Flat(ref x) if x.is_empty() && self.1.empty_string_as_none => {
+                visitor.visit_none()
+            }
             _ => visitor.visit_some(self),
         }
     }
@@ -591,7 +2157,7 @@ impl<'de> de::Deserializer<'de> for LevelDeserializer<'de> {
         V: de::Visitor<'de>,
     {
         match self.0 {
-            Level::Flat(ref x) if x == "" => visitor.visit_unit(),
+            Level::Flat(ref x) if x.is_empty() => visitor.visit_unit(),
             _ => Err(de::Error::custom("expected unit".to_owned())),
         }
     }
@@ -607,7 +2173,7 @@ impl<'de> de::Deserializer<'de> for LevelDeserializer<'de> {
     {
         match self.0 {
             Level::Nested(map) => {
-                QsDeserializer::with_map(map).deserialize_enum(name, variants, visitor)
+                QsDeserializer::with_map(map, self.1).deserialize_enum(name, variants, visitor)
             }
             Level::Flat(_) => visitor.visit_enum(self),
             x => Err(de::Error::custom(format!(
@@ -624,12 +2190,14 @@ impl<'de> de::Deserializer<'de> for LevelDeserializer<'de> {
     {
         match self.0 {
             Level::Nested(_) => self.into_deserializer()?.deserialize_map(visitor),
-            Level::OrderedSeq(map) => visitor.visit_seq(LevelSeq(map.into_values())),
-            Level::Sequence(seq) => visitor.visit_seq(LevelSeq(seq.into_iter())),
+            Level::OrderedSeq(map) => visitor.visit_seq(LevelSeq(map.into_iter(), self.1)),
+            Level::Sequence(seq) => {
+                visitor.visit_seq(LevelSeq(seq.into_iter().enumerate(), self.1))
+            }
             Level::Flat(_) => {
                 // For a newtype_struct, attempt to deserialize a flat value as a
                 // single element sequence.
-                visitor.visit_seq(LevelSeq(vec![self.0].into_iter()))
+                visitor.visit_seq(LevelSeq(Some((0, self.0)).into_iter(), self.1))
             }
             Level::Invalid(e) => Err(de::Error::custom(e)),
             Level::Uninitialised => Err(de::Error::custom(
@@ -652,32 +2220,111 @@ impl<'de> de::Deserializer<'de> for LevelDeserializer<'de> {
         }
     }
 
-    deserialize_primitive!(bool, deserialize_bool, visit_bool);
-    deserialize_primitive!(i8, deserialize_i8, visit_i8);
-    deserialize_primitive!(i16, deserialize_i16, visit_i16);
-    deserialize_primitive!(i32, deserialize_i32, visit_i32);
-    deserialize_primitive!(i64, deserialize_i64, visit_i64);
-    deserialize_primitive!(u8, deserialize_u8, visit_u8);
-    deserialize_primitive!(u16, deserialize_u16, visit_u16);
-    deserialize_primitive!(u32, deserialize_u32, visit_u32);
-    deserialize_primitive!(u64, deserialize_u64, visit_u64);
-    deserialize_primitive!(f32, deserialize_f32, visit_f32);
-    deserialize_primitive!(f64, deserialize_f64, visit_f64);
+    deserialize_primitive!(deserialize_bool);
+    deserialize_primitive!(deserialize_i8);
+    deserialize_primitive!(deserialize_i16);
+    deserialize_primitive!(deserialize_i32);
+    deserialize_primitive!(deserialize_i64);
+    deserialize_primitive!(deserialize_u8);
+    deserialize_primitive!(deserialize_u16);
+    deserialize_primitive!(deserialize_u32);
+    deserialize_primitive!(deserialize_u64);
+    deserialize_primitive!(deserialize_f32);
+    deserialize_primitive!(deserialize_f64);
 
-    forward_to_deserialize_any! {
-        char
-        str
-        string
+    /// Flat values are decoded as base64, accepting both the standard and
+    /// the URL-safe alphabets.
+    #[cfg(feature = "base64")]
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.0 {
+            Level::Flat(x) => {
+                match base64::decode(x.as_bytes())
+                    .or_else(|_| base64::decode_config(x.as_bytes(), base64::URL_SAFE))
+                {
+                    Ok(bytes) => visitor.visit_byte_buf(bytes),
+                    Err(e) => Err(de::Error::custom(format!("invalid base64: {}", e))),
+                }
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    #[cfg(feature = "base64")]
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    #[cfg(not(feature = "base64"))]
+    serde::forward_to_deserialize_any! {
         bytes
         byte_buf
+    }
+
+    /// Flat values are always visited as strings, whatever the coercion.
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.0 {
+            Level::Flat(Cow::Owned(s)) => visitor.visit_string(s),
+            Level::Flat(Cow::Borrowed(s)) => visitor.visit_borrowed_str(s),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    /// Accepts any level, except for invalid ones whose error is returned so
+    /// that ignoring a value still validates it.
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.0 {
+            Level::Invalid(e) => Err(de::Error::custom(e)),
+            Level::Uninitialised => visitor.visit_unit(),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    /// A flat value is a sequence of one element, as for a key which
+    /// appears only once among repeated keys.
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.0 {
+            Level::Flat(_) => visitor.visit_seq(LevelSeq(Some((0, self.0)).into_iter(), self.1)),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
         unit_struct
         // newtype_struct
         tuple_struct
         struct
         identifier
         tuple
-        ignored_any
-        seq
+        // seq
         // map
     }
 }
@@ -686,16 +2333,81 @@ macro_rules! forward_parsable_to_deserialize_any {
     ($($ty:ident => $meth:ident,)*) => {
         $(
             fn $meth<V>(self, visitor: V) -> Result<V::Value> where V: de::Visitor<'de> {
+                if self.1.coercion == Coercion::AllStr {
+                    return self.deserialize_any(visitor);
+                }
                 match self.0.parse::<$ty>() {
                     Ok(val) => val.into_deserializer().$meth(visitor),
                     Err(e) => Err(de::Error::custom(e))
                 }
             }
         )*
+    };
+    (lenient $($ty:ident => $meth:ident,)*) => {
+        $(
+            fn $meth<V>(self, visitor: V) -> Result<V::Value> where V: de::Visitor<'de> {
+                if self.1.coercion == Coercion::AllStr {
+                    return self.deserialize_any(visitor);
+                }
+                match self.0.parse::<$ty>() {
+                    Ok(val) => val.into_deserializer().$meth(visitor),
+                    Err(e) => match self.1.coercion {
+                        Coercion::Lenient => match parse_truncated::<$ty>(&self.0) {
+                            Some(val) => val.into_deserializer().$meth(visitor),
+                            None => Err(de::Error::custom(e)),
+                        },
+                        _ => Err(de::Error::custom(e)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+/// Visits `value` as an integer, float or boolean if it looks like one, and
+/// as a string otherwise.
+fn visit_natural<'de, V>(value: Cow<'de, str>, visitor: V) -> Result<V::Value>
+where
+    V: de::Visitor<'de>,
+{
+    if let Ok(v) = value.parse::<i64>() {
+        return visitor.visit_i64(v);
+    }
+    if let Ok(v) = value.parse::<u64>() {
+        return visitor.visit_u64(v);
+    }
+    // Only plain decimal notation, so that e.g. `inf` and `NaN` stay strings.
+    let is_decimal = value.bytes().any(|b| b.is_ascii_digit())
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'-' | b'+' | b'e' | b'E'));
+    if is_decimal {
+        if let Ok(v) = value.parse::<f64>() {
+            return visitor.visit_f64(v);
+        }
+    }
+    match &*value {
+        "true" => visitor.visit_bool(true),
+        "false" => visitor.visit_bool(false),
+        _ => match value {
+            Cow::Owned(s) => visitor.visit_string(s),
+            Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+        },
+    }
+}
+
+/// Parses `input` as a float and truncates it to an integer of type `T`,
+/// if it is in range.
+fn parse_truncated<T: TryFrom<i128>>(input: &str) -> Option<T> {
+    let value = input.parse::<f64>().ok()?.trunc();
+    if value.is_finite() && value.abs() < i128::MAX as f64 {
+        T::try_from(value as i128).ok()
+    } else {
+        None
     }
 }
 
-struct ParsableStringDeserializer<'a>(Cow<'a, str>);
+struct ParsableStringDeserializer<'a>(Cow<'a, str>, Config);
 
 impl<'de> de::Deserializer<'de> for ParsableStringDeserializer<'de> {
     type Error = Error;
@@ -716,10 +2428,30 @@ impl<'de> de::Deserializer<'de> for ParsableStringDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_enum(LevelDeserializer(Level::Flat(self.0)))
+        visitor.visit_enum(LevelDeserializer(Level::Flat(self.0), self.1))
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        if self.1.coercion == Coercion::AllStr {
+            return self.deserialize_any(visitor);
+        }
+        let (true_strings, false_strings) = self.1.bool_strings;
+        if true_strings.contains(&&*self.0) {
+            visitor.visit_bool(true)
+        } else if false_strings.contains(&&*self.0) {
+            visitor.visit_bool(false)
+        } else {
+            Err(de::Error::custom(format!(
+                "invalid boolean: {:?}, expected one of {:?} or {:?}",
+                self.0, true_strings, false_strings
+            )))
+        }
     }
 
-    forward_to_deserialize_any! {
+    serde::forward_to_deserialize_any! {
         map
         struct
         seq
@@ -739,7 +2471,12 @@ impl<'de> de::Deserializer<'de> for ParsableStringDeserializer<'de> {
     }
 
     forward_parsable_to_deserialize_any! {
-        bool => deserialize_bool,
+        f32 => deserialize_f32,
+        f64 => deserialize_f64,
+    }
+
+    forward_parsable_to_deserialize_any! {
+        lenient
         u8 => deserialize_u8,
         u16 => deserialize_u16,
         u32 => deserialize_u32,
@@ -748,7 +2485,5 @@ impl<'de> de::Deserializer<'de> for ParsableStringDeserializer<'de> {
         i16 => deserialize_i16,
         i32 => deserialize_i32,
         i64 => deserialize_i64,
-        f32 => deserialize_f32,
-        f64 => deserialize_f64,
     }
 }
@@ -1,12 +1,13 @@
-use crate::utils::{replace_space, QS_ENCODE_SET};
-
 use super::*;
 
-use percent_encoding::percent_encode;
+use crate::utils::build_key_path;
+
+use indexmap::map::Entry as MapEntry;
 use serde::de;
 
 use std::borrow::Cow;
 use std::iter::Iterator;
+use std::mem;
 use std::slice::Iter;
 use std::str;
 
@@ -24,15 +25,29 @@ impl<'a> Level<'a> {
     /// `value` for key `key`.
     /// Returns error if `self` is not a map, or already has an entry for that
     /// key.
-    fn insert_map_value(&mut self, key: Cow<'a, str>, value: Cow<'a, str>) {
+    /// With `RepeatedKeys::AsSequence`, a repeated key instead collects its
+    /// flat values into a sequence.
+    fn insert_map_value(&mut self, key: Cow<'a, str>, value: Cow<'a, str>, repeated: RepeatedKeys) {
         if let Level::Nested(ref mut map) = *self {
             match map.entry(key) {
-                Entry::Occupied(mut o) => {
+                MapEntry::Occupied(mut o) => {
+                    if repeated == RepeatedKeys::AsSequence {
+                        match *o.get_mut() {
+                            Level::Sequence(ref mut seq) => {
+                                seq.push(Level::Flat(value));
+                                return;
+                            }
+                            ref mut level @ Level::Flat(_) => {
+                                let first = mem::replace(level, Level::Uninitialised);
+                                *level = Level::Sequence(vec![first, Level::Flat(value)]);
+                                return;
+                            }
+                            _ => {}
+                        }
+                    }
                     let key = o.key();
                     let error = if key.contains('[') {
-                        let newkey = percent_encode(key.as_bytes(), QS_ENCODE_SET)
-                            .map(replace_space)
-                            .collect::<String>();
+                        let newkey = encode(key);
                         format!("Multiple values for one key: \"{}\"\nInvalid field contains an encoded bracket -- did you mean to use non-strict mode?\n  https://docs.rs/serde_qs/latest/serde_qs/#strict-vs-non-strict-modes", newkey)
                     } else {
                         format!("Multiple values for one key: \"{}\"", key)
@@ -40,13 +55,13 @@ impl<'a> Level<'a> {
                     // Throw away old result; map is now invalid anyway.
                     let _ = o.insert(Level::Invalid(error));
                 }
-                Entry::Vacant(vm) => {
+                MapEntry::Vacant(vm) => {
                     // Map is empty, result is None
                     let _ = vm.insert(Level::Flat(value));
                 }
             }
         } else if let Level::Uninitialised = *self {
-            let mut map = BTreeMap::default();
+            let mut map = IndexMap::default();
             let _ = map.insert(key, Level::Flat(value));
             *self = Level::Nested(map);
         } else {
@@ -58,6 +73,19 @@ impl<'a> Level<'a> {
         }
     }
 
+    /// Sorts the keys of every map at or below this level.
+    fn sort_keys(&mut self) {
+        match *self {
+            Level::Nested(ref mut map) => {
+                map.sort_keys();
+                map.values_mut().for_each(Level::sort_keys);
+            }
+            Level::OrderedSeq(ref mut map) => map.values_mut().for_each(Level::sort_keys),
+            Level::Sequence(ref mut seq) => seq.iter_mut().for_each(Level::sort_keys),
+            _ => {}
+        }
+    }
+
     /// If this `Level` value is indeed a seq, then push a new value
     fn insert_ord_seq_value(&mut self, key: usize, value: Cow<'a, str>) {
         if let Level::OrderedSeq(ref mut map) = *self {
@@ -117,9 +145,11 @@ pub struct Parser<'a> {
     index: usize,
     acc: (usize, usize),
     peeked: Option<&'a u8>,
-    depth: usize, // stores the current depth, for use in bounded-depth parsing
+    depth: usize,  // stores the current depth, for use in bounded-depth parsing
+    values: usize, // counts the values parsed, for `Config::max_value_count`
     strict: bool,
     state: ParsingState,
+    config: Config,
 }
 
 /// The parsing logic varies slightly based on whether it is a key or a value
@@ -200,7 +230,7 @@ impl Parser<'_> {
 
 /// Replace b'+' with b' '
 /// Copied from [`form_urlencoded`](https://github.com/servo/rust-url/blob/380be29859adb859e861c2d765897c22ec878e01/src/form_urlencoded.rs#L125).
-fn replace_plus(input: &[u8]) -> Cow<[u8]> {
+fn replace_plus(input: &[u8]) -> Cow<'_, [u8]> {
     match input.iter().position(|&b| b == b'+') {
         None => Cow::Borrowed(input),
         Some(first_position) => {
@@ -218,16 +248,18 @@ fn replace_plus(input: &[u8]) -> Cow<[u8]> {
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(encoded: &'a [u8], depth: usize, strict: bool) -> Self {
+    pub fn new(encoded: &'a [u8], config: Config) -> Self {
         Parser {
             inner: encoded,
             iter: encoded.iter(),
             acc: (0, 0),
             index: 0,
             peeked: None,
-            depth,
-            strict,
+            depth: config.max_depth,
+            values: 0,
+            strict: config.strict,
             state: ParsingState::Init,
+            config,
         }
     }
 
@@ -241,10 +273,20 @@ impl<'a> Parser<'a> {
     /// Avoids allocations when neither percent encoded, nor `'+'` values are
     /// present.
     fn collect_str(&mut self) -> Result<Cow<'a, str>> {
-        let replaced = replace_plus(&self.inner[self.acc.0..self.acc.1 - 1]);
+        if !self.config.percent_decode {
+            let res = str::from_utf8(&self.inner[self.acc.0..self.acc.1 - 1]);
+            self.clear_acc();
+            return Ok(Cow::Borrowed(res?));
+        }
+        let encoded = &self.inner[self.acc.0..self.acc.1 - 1];
+        let replaced = if self.config.plus_as_space {
+            replace_plus(encoded)
+        } else {
+            Cow::Borrowed(encoded)
+        };
         let decoder = percent_encoding::percent_decode(&replaced);
 
-        let maybe_decoded = if self.strict {
+        let maybe_decoded = if self.config.is_strict_utf8() {
             decoder.decode_utf8()?
         } else {
             decoder.decode_utf8_lossy()
@@ -268,22 +310,124 @@ impl<'a> Parser<'a> {
             Cow::Owned(owned) => Ok(Cow::Owned(owned)),
         };
         self.clear_acc();
-        ret.map_err(Error::from)
+        ret
     }
 
     /// In some ways the main way to use a `Parser`, this runs the parsing step
     /// and outputs a simple `Deserializer` over the parsed map.
     pub(crate) fn as_deserializer(&mut self) -> Result<QsDeserializer<'a>> {
-        let map = BTreeMap::default();
-        let mut root = Level::Nested(map);
+        if self.config.pair_separator == self.config.key_value_separator {
+            return Err(de::Error::custom(format!(
+                "{:?} cannot separate both pairs and keys from values",
+                self.config.pair_separator as char
+            )));
+        }
+
+        // The root is usually a map, but may also be a sequence when the
+        // keys start with brackets, e.g. `[0][a]=1`.
+        let mut root = Level::Uninitialised;
 
         // Parses all top level nodes into the `root` map.
-        while self.parse(&mut root)? {}
-        let iter = match root {
-            Level::Nested(map) => map.into_iter(),
-            _ => BTreeMap::default().into_iter(),
+        loop {
+            self.check_bracket_depth()?;
+            self.check_lengths()?;
+            if !self.parse(&mut root)? {
+                break;
+            }
+            check_key_limit(&root, &self.config, self.index)?;
+        }
+        root_deserializer(root, self.config)
+    }
+
+    /// Checks that the key and value of the next pair are no longer than
+    /// allowed by `Config::key_length_limit` and `Config::value_length_limit`.
+    fn check_lengths(&self) -> Result<()> {
+        let rest = self.iter.as_slice();
+        let pair = match rest.iter().position(|&b| b == self.config.pair_separator) {
+            Some(end) => &rest[..end],
+            None => rest,
+        };
+        let (key_len, value_len) = match pair
+            .iter()
+            .position(|&b| b == self.config.key_value_separator)
+        {
+            Some(end) => (end, pair.len() - end - 1),
+            None => (pair.len(), 0),
+        };
+        check_lengths(key_len, value_len, &self.config, self.index)
+    }
+
+    /// Inserts `value` for `key` into `node`, first checking the index it
+    /// would have if the key is repeated.
+    fn insert_map_value(
+        &mut self,
+        node: &mut Level<'a>,
+        key: Cow<'a, str>,
+        value: Cow<'a, str>,
+    ) -> Result<()> {
+        self.count_value()?;
+        insert_map_value(node, key, value, &self.config, self.index)
+    }
+
+    fn insert_ord_seq_value(
+        &mut self,
+        node: &mut Level<'a>,
+        key: usize,
+        value: Cow<'a, str>,
+    ) -> Result<()> {
+        self.count_value()?;
+        node.insert_ord_seq_value(key, value);
+        Ok(())
+    }
+
+    fn insert_seq_value(&mut self, node: &mut Level<'a>, value: Cow<'a, str>) -> Result<()> {
+        self.count_value()?;
+        node.insert_seq_value(value);
+        Ok(())
+    }
+
+    /// Counts a value towards `Config::max_value_count`.
+    fn count_value(&mut self) -> Result<()> {
+        self.values += 1;
+        check_value_count(self.values, &self.config, self.index)
+    }
+
+    /// Checks that a sequence index is within `Config::array_limit`.
+    fn check_array_index(&self, index: usize) -> Result<()> {
+        check_array_index(index, &self.config, self.index)
+    }
+
+    /// Checks that the key of the next pair does not contain more bracketed
+    /// segments than allowed by `Config::bracket_depth_limit`.
+    fn check_bracket_depth(&self) -> Result<()> {
+        let limit = match self.config.bracket_depth_limit {
+            Some(limit) => limit,
+            None => return Ok(()),
         };
-        Ok(QsDeserializer { iter, value: None })
+        let rest = self.iter.as_slice();
+        let key = match rest
+            .iter()
+            .position(|&b| b == self.config.key_value_separator || b == self.config.pair_separator)
+        {
+            Some(end) => &rest[..end],
+            None => rest,
+        };
+        let mut brackets = key.iter().filter(|&&b| b == b'[').count();
+        if !self.strict {
+            // Encoded brackets are parsed as brackets in non-strict mode.
+            brackets += key.windows(3).filter(|w| w == b"%5B").count();
+        }
+        if brackets > limit {
+            Err(super::Error::parse_err(
+                format!(
+                    "key has {} bracketed segments, more than the limit of {}",
+                    brackets, limit
+                ),
+                self.index,
+            ))
+        } else {
+            Ok(())
+        }
     }
 
     /// This is the top level parsing function. It checks the first character to
@@ -294,8 +438,19 @@ impl<'a> Parser<'a> {
     fn parse(&mut self, node: &mut Level<'a>) -> Result<bool> {
         // First character determines parsing type
         if self.depth == 0 {
+            match self.peek() {
+                // Ran out of characters to parse
+                None => return Ok(false),
+                // Skip empty byte sequences, as below
+                Some(&c) if c == self.config.pair_separator => {
+                    let _ = self.next();
+                    self.clear_acc();
+                    return Ok(true);
+                }
+                _ => {}
+            }
             // Hit the maximum depth level, so parse everything as a key
-            let key = self.parse_key(b'=', false)?;
+            let key = self.parse_key(self.config.key_value_separator, false)?;
             self.parse_map_value(key, node)?;
             return Ok(true);
         }
@@ -321,6 +476,18 @@ impl<'a> Parser<'a> {
                                     // throw away the bracket
                                     let _ = self.next();
                                     self.clear_acc();
+                                    // A repeated key such as `a=1&a[]=2`.
+                                    if let (RepeatedKeys::AsSequence, Level::Flat(_)) =
+                                        (self.config.repeated_keys, &*node)
+                                    {
+                                        let first = mem::replace(node, Level::Uninitialised);
+                                        *node = Level::Sequence(vec![first]);
+                                    }
+                                    let index = match *node {
+                                        Level::Sequence(ref seq) => seq.len(),
+                                        _ => 0,
+                                    };
+                                    self.check_array_index(index)?;
                                     self.parse_seq_value(node)?;
                                     return Ok(true);
                                 }
@@ -328,6 +495,7 @@ impl<'a> Parser<'a> {
                                 b'0'..=b'9' => {
                                     let key = self.parse_key(b']', true)?;
                                     let key = key.parse().map_err(Error::from)?;
+                                    self.check_array_index(key)?;
                                     self.parse_ord_seq_value(key, node)?;
                                     return Ok(true);
                                 }
@@ -354,7 +522,7 @@ impl<'a> Parser<'a> {
                         }
                     }
                     // Skip empty byte sequences (e.g. leading `&`, trailing `&`, `&&`, ...)
-                    b'&' => {
+                    c if c == self.config.pair_separator => {
                         self.clear_acc();
                         Ok(true)
                     }
@@ -396,21 +564,17 @@ impl<'a> Parser<'a> {
                         }
                         return self.collect_str();
                     }
-                    b'=' => {
-                        // Allow the '=' byte only when parsing keys within []
-                        if end_on != b']' {
-                            // Otherwise, we have reached the end of the key
-                            // Add this character back to the buffer for peek.
-                            self.peeked = Some(x);
-                            return self.collect_str();
-                        }
-
-                        // otherwise do nothing, so '=' is accumulated
+                    // Allow the '=' byte only when parsing keys within []
+                    c if c == self.config.key_value_separator && end_on != b']' => {
+                        // Otherwise, we have reached the end of the key
+                        // Add this character back to the buffer for peek.
+                        self.peeked = Some(x);
+                        return self.collect_str();
                     }
-                    b'&' => {
+                    c if c == self.config.pair_separator => {
                         // important to keep the `&` character so we know the
                         // key-value is of the form `key&..=` (i.e. no value)
-                        self.peeked = Some(&b'&');
+                        self.peeked = Some(x);
                         return self.collect_str();
                     }
                     _ => {
@@ -432,25 +596,24 @@ impl<'a> Parser<'a> {
         let res = loop {
             if let Some(x) = self.peek() {
                 match *x {
-                    b'=' => {
+                    c if c == self.config.key_value_separator => {
                         // Key is finished, parse up until the '&' as the value
                         self.clear_acc();
                         self.state = ParsingState::Value;
-                        for _ in self.take_while(|b| *b != &b'&') {}
+                        let separator = self.config.pair_separator;
+                        for _ in self.take_while(|&&b| b != separator) {}
                         let value: Cow<'a, str> = self.collect_str()?;
-                        node.insert_map_value(key, value);
-                        break Ok(());
+                        break self.insert_map_value(node, key, value);
                     }
-                    b'&' => {
+                    c if c == self.config.pair_separator => {
                         // No value
-                        node.insert_map_value(key, Cow::Borrowed(""));
-                        break Ok(());
+                        break self.insert_map_value(node, key, Cow::Borrowed(""));
                     }
                     b'[' => {
                         // The key continues to another level of nested.
                         // Add a new unitialised level for this node and continue.
                         if let Level::Uninitialised = *node {
-                            *node = Level::Nested(BTreeMap::default());
+                            *node = Level::Nested(IndexMap::default());
                         }
                         if let Level::Nested(ref mut map) = *node {
                             // By parsing we drop down another level
@@ -458,8 +621,11 @@ impl<'a> Parser<'a> {
                             // Either take the existing entry, or add a new
                             // unitialised level
                             // Use this new node to keep parsing
-                            let _ = self.parse(map.entry(key).or_insert(Level::Uninitialised))?;
-                            break Ok(());
+                            let res = self.parse(map.entry(key).or_insert(Level::Uninitialised));
+                            // We have finished parsing the level below, so go
+                            // back up a level.
+                            self.depth += 1;
+                            break res.map(|_| ());
                         } else {
                             // We expected to parse into a map here.
                             break Err(super::Error::parse_err(
@@ -476,11 +642,8 @@ impl<'a> Parser<'a> {
                         // Anything else is unexpected since we just finished
                         // parsing a key.
                         if self.strict {
-                            break Err(super::Error::parse_err(
-                                format!(
-                                    "Unexpected character: '{}' found when parsing",
-                                    String::from_utf8_lossy(&[c])
-                                ),
+                            break Err(unexpected_character(
+                                &String::from_utf8_lossy(&[c]),
                                 self.index,
                             ));
                         } else {
@@ -490,12 +653,9 @@ impl<'a> Parser<'a> {
                 }
             } else {
                 // The string has ended, so the value is empty.
-                node.insert_map_value(key, Cow::Borrowed(""));
-                break Ok(());
+                break self.insert_map_value(node, key, Cow::Borrowed(""));
             }
         };
-        // We have finished parsing this level, so go back up a level.
-        self.depth += 1;
         res
     }
 
@@ -508,20 +668,19 @@ impl<'a> Parser<'a> {
         let res = loop {
             if let Some(x) = self.peek() {
                 match *x {
-                    b'=' => {
+                    c if c == self.config.key_value_separator => {
                         // Key is finished, parse up until the '&' as the value
                         self.clear_acc();
                         self.state = ParsingState::Value;
-                        for _ in self.take_while(|b| *b != &b'&') {}
+                        let separator = self.config.pair_separator;
+                        for _ in self.take_while(|&&b| b != separator) {}
                         let value = self.collect_str()?;
                         // Reached the end of the key string
-                        node.insert_ord_seq_value(key, value);
-                        break Ok(());
+                        break self.insert_ord_seq_value(node, key, value);
                     }
-                    b'&' => {
+                    c if c == self.config.pair_separator => {
                         // No value
-                        node.insert_ord_seq_value(key, Cow::Borrowed(""));
-                        break Ok(());
+                        break self.insert_ord_seq_value(node, key, Cow::Borrowed(""));
                     }
                     b'[' => {
                         // The key continues to another level of nested.
@@ -532,13 +691,16 @@ impl<'a> Parser<'a> {
                         if let Level::OrderedSeq(ref mut map) = *node {
                             // By parsing we drop down another level
                             self.depth -= 1;
-                            let _ = self.parse(
+                            let res = self.parse(
                                 // Either take the existing entry, or add a new
                                 // unitialised level
                                 // Use this new node to keep parsing
                                 map.entry(key).or_insert(Level::Uninitialised),
-                            )?;
-                            break Ok(());
+                            );
+                            // We have finished parsing the level below, so go
+                            // back up a level.
+                            self.depth += 1;
+                            break res.map(|_| ());
                         } else {
                             // We expected to parse into a seq here.
                             break Err(super::Error::parse_err(
@@ -555,8 +717,8 @@ impl<'a> Parser<'a> {
                         // Anything else is unexpected since we just finished
                         // parsing a key.
                         if self.strict {
-                            break Err(super::Error::parse_err(
-                                format!("Unexpected character: {:?} found when parsing", c),
+                            break Err(unexpected_character(
+                                &String::from_utf8_lossy(&[c]),
                                 self.index,
                             ));
                         } else {
@@ -566,12 +728,9 @@ impl<'a> Parser<'a> {
                 }
             } else {
                 // The string has ended, so the value is empty.
-                node.insert_ord_seq_value(key, Cow::Borrowed(""));
-                break Ok(());
+                break self.insert_ord_seq_value(node, key, Cow::Borrowed(""));
             }
         };
-        // We have finished parsing this level, so go back up a level.
-        self.depth += 1;
         res
     }
 
@@ -583,19 +742,18 @@ impl<'a> Parser<'a> {
         let res = match self.peek() {
             Some(x) => {
                 match *x {
-                    b'=' => {
+                    c if c == self.config.key_value_separator => {
                         // Key is finished, parse up until the '&' as the value
                         self.clear_acc();
                         self.state = ParsingState::Value;
-                        for _ in self.take_while(|b| *b != &b'&') {}
+                        let separator = self.config.pair_separator;
+                        for _ in self.take_while(|&&b| b != separator) {}
                         let value = self.collect_str()?;
-                        node.insert_seq_value(value);
-                        Ok(())
+                        self.insert_seq_value(node, value)
                     }
-                    b'&' => {
+                    c if c == self.config.pair_separator => {
                         // key value is empty
-                        node.insert_seq_value(Cow::Borrowed(""));
-                        Ok(())
+                        self.insert_seq_value(node, Cow::Borrowed(""))
                     }
                     _ => Err(super::Error::parse_err(
                         "non-indexed sequence of \
@@ -606,12 +764,314 @@ impl<'a> Parser<'a> {
             }
             None => {
                 // The string has ended, so the value is empty.
-                node.insert_seq_value(Cow::Borrowed(""));
-                Ok(())
+                self.insert_seq_value(node, Cow::Borrowed(""))
             }
         };
-        // We have finished parsing this level, so go back up a level.
-        self.depth += 1;
         res
     }
 }
+
+/// Returns a `Deserializer` over already decoded `(key, value)` pairs, whose
+/// keys are split at brackets just as when parsing a querystring.
+///
+/// The position in errors is the index of the offending pair.
+pub(crate) fn pairs_deserializer<K, V>(
+    pairs: &[(K, V)],
+    config: Config,
+) -> Result<QsDeserializer<'_>>
+where
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    let mut root = Level::Uninitialised;
+    for (position, (key, value)) in pairs.iter().enumerate() {
+        check_value_count(position + 1, &config, position)?;
+        let (key, value) = (key.as_ref(), value.as_ref());
+        check_lengths(key.len(), value.len(), &config, position)?;
+        let segments = key_segments(key, &config, position)?;
+        if let Some(limit) = config.bracket_depth_limit {
+            let brackets = segments.len() - 1;
+            if brackets > limit {
+                return Err(super::Error::parse_err(
+                    format!(
+                        "key has {} bracketed segments, more than the limit of {}",
+                        brackets, limit
+                    ),
+                    position,
+                ));
+            }
+        }
+        insert_pair(
+            &mut root,
+            &segments,
+            true,
+            Cow::Borrowed(value),
+            config.max_depth,
+            &config,
+            position,
+        )?;
+        check_key_limit(&root, &config, position)?;
+    }
+    root_deserializer(root, config)
+}
+
+/// Splits `key` into its segments as `Parser::parse` would: within
+/// `Config::max_depth`, text following a closing bracket is an error in
+/// strict mode, and otherwise skipped up to the next opening bracket.
+fn key_segments<'a>(key: &'a str, config: &Config, position: usize) -> Result<Vec<&'a str>> {
+    let (root, mut rest) = match key.find('[') {
+        Some(idx) => (&key[..idx], &key[idx..]),
+        None => (key, ""),
+    };
+    let mut segments = vec![root];
+    while let Some(c) = rest.chars().next() {
+        if c != '[' {
+            if segments.len() > config.max_depth {
+                // Past the maximum depth the rest is kept as a single key.
+                segments.push(rest);
+                break;
+            }
+            if config.strict {
+                return Err(unexpected_character(&rest[..c.len_utf8()], position));
+            }
+            rest = rest.find('[').map_or("", |idx| &rest[idx..]);
+            continue;
+        }
+        let stripped = &rest[1..];
+        match stripped.find(']') {
+            Some(end) => {
+                segments.push(&stripped[..end]);
+                rest = &stripped[end + 1..];
+            }
+            None => {
+                segments.push(stripped);
+                rest = "";
+            }
+        }
+    }
+    Ok(segments)
+}
+
+/// The error for a character other than `[` or `=` following a closing
+/// bracket.
+fn unexpected_character(c: &str, position: usize) -> Error {
+    super::Error::parse_err(
+        format!("Unexpected character: '{}' found when parsing", c),
+        position,
+    )
+}
+
+/// Inserts `value` below `node` at the path of `segments`, following
+/// `Parser::parse`: empty segments are unindexed sequences, integer segments
+/// are sequence indices, and any others are map keys. The `root` segment is
+/// always a map key.
+fn insert_pair<'a>(
+    node: &mut Level<'a>,
+    segments: &[&'a str],
+    root: bool,
+    value: Cow<'a, str>,
+    depth: usize,
+    config: &Config,
+    position: usize,
+) -> Result<()> {
+    let (&segment, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => return Ok(()),
+    };
+    if depth == 0 {
+        // Hit the maximum depth level, so the rest is a single key, with its
+        // brackets unless it is the root.
+        let key = if root {
+            build_key_path(segments)
+        } else {
+            let mut parts = vec![""];
+            parts.extend_from_slice(segments);
+            build_key_path(&parts)
+        };
+        return insert_map_value(node, Cow::Owned(key), value, config, position);
+    }
+    match segment.bytes().next() {
+        None if !root => {
+            if !rest.is_empty() {
+                return Err(super::Error::parse_err(
+                    "non-indexed sequence of structs not supported",
+                    position,
+                ));
+            }
+            // A repeated key such as `a=1&a[]=2`.
+            if let (RepeatedKeys::AsSequence, Level::Flat(_)) = (config.repeated_keys, &*node) {
+                let first = mem::replace(node, Level::Uninitialised);
+                *node = Level::Sequence(vec![first]);
+            }
+            let index = match *node {
+                Level::Sequence(ref seq) => seq.len(),
+                _ => 0,
+            };
+            check_array_index(index, config, position)?;
+            node.insert_seq_value(value);
+            Ok(())
+        }
+        Some(b'0'..=b'9') if !root => {
+            let index = segment.parse().map_err(Error::from)?;
+            check_array_index(index, config, position)?;
+            if rest.is_empty() {
+                node.insert_ord_seq_value(index, value);
+                return Ok(());
+            }
+            if let Level::Uninitialised = *node {
+                *node = Level::OrderedSeq(BTreeMap::default());
+            }
+            match *node {
+                Level::OrderedSeq(ref mut map) => insert_pair(
+                    map.entry(index).or_insert(Level::Uninitialised),
+                    rest,
+                    false,
+                    value,
+                    depth - 1,
+                    config,
+                    position,
+                ),
+                _ => Err(super::Error::parse_err(
+                    format!("tried to insert a new key into {:?}", node),
+                    position,
+                )),
+            }
+        }
+        _ => {
+            if rest.is_empty() {
+                return insert_map_value(node, Cow::Borrowed(segment), value, config, position);
+            }
+            if let Level::Uninitialised = *node {
+                *node = Level::Nested(IndexMap::default());
+            }
+            match *node {
+                Level::Nested(ref mut map) => insert_pair(
+                    map.entry(Cow::Borrowed(segment))
+                        .or_insert(Level::Uninitialised),
+                    rest,
+                    false,
+                    value,
+                    depth - 1,
+                    config,
+                    position,
+                ),
+                _ => Err(super::Error::parse_err(
+                    format!("tried to insert a new key into {:?}", node),
+                    position,
+                )),
+            }
+        }
+    }
+}
+
+/// Inserts `value` for `key` into `node`, first checking the index it would
+/// have if the key is repeated.
+fn insert_map_value<'a>(
+    node: &mut Level<'a>,
+    key: Cow<'a, str>,
+    value: Cow<'a, str>,
+    config: &Config,
+    position: usize,
+) -> Result<()> {
+    let repeated = config.repeated_keys;
+    if let (RepeatedKeys::AsSequence, Level::Nested(ref map)) = (repeated, &*node) {
+        match map.get(&key) {
+            Some(Level::Flat(_)) => check_array_index(1, config, position)?,
+            Some(Level::Sequence(seq)) => check_array_index(seq.len(), config, position)?,
+            _ => {}
+        }
+    }
+    node.insert_map_value(key, value, repeated);
+    Ok(())
+}
+
+/// Checks a key and value against `Config::key_length_limit` and
+/// `Config::value_length_limit`.
+fn check_lengths(key_len: usize, value_len: usize, config: &Config, position: usize) -> Result<()> {
+    let limit = config.key_length_limit;
+    if key_len > limit {
+        return Err(super::Error::parse_err(
+            format!("key length {} exceeds limit {}", key_len, limit),
+            position,
+        ));
+    }
+    let limit = config.value_length_limit;
+    if value_len > limit {
+        return Err(super::Error::parse_err(
+            format!("value length {} exceeds limit {}", value_len, limit),
+            position,
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that a sequence index is within `Config::array_limit`.
+fn check_array_index(index: usize, config: &Config, position: usize) -> Result<()> {
+    match config.array_limit {
+        Some(limit) if index > limit => Err(super::Error::parse_err(
+            format!(
+                "array index {} exceeds configured limit of {}",
+                index, limit
+            ),
+            position,
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Checks that no more than `Config::max_value_count` values were parsed.
+fn check_value_count(values: usize, config: &Config, position: usize) -> Result<()> {
+    if values > config.max_value_count {
+        return Err(super::Error::parse_err(
+            format!("exceeded the limit of {} values", config.max_value_count),
+            position,
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that `root` has no more keys than allowed by `Config::key_limit`.
+fn check_key_limit(root: &Level<'_>, config: &Config, position: usize) -> Result<()> {
+    let keys = match *root {
+        Level::Nested(ref map) => map.len(),
+        Level::OrderedSeq(ref map) => map.len(),
+        Level::Sequence(ref seq) => seq.len(),
+        _ => 0,
+    };
+    if keys > config.key_limit {
+        return Err(super::Error::parse_err(
+            format!("exceeded the limit of {} keys", config.key_limit),
+            position,
+        ));
+    }
+    Ok(())
+}
+
+/// Returns a `Deserializer` over the top-level entries of `root`.
+fn root_deserializer(mut root: Level<'_>, config: Config) -> Result<QsDeserializer<'_>> {
+    if config.key_order == KeyOrder::Sorted {
+        root.sort_keys();
+    }
+    let iter = match root {
+        Level::Nested(map) => map.into_iter(),
+        // Sequence indices become keys, just as for nested sequences.
+        Level::OrderedSeq(map) => map
+            .into_iter()
+            .map(|(k, v)| (Cow::Owned(k.to_string()), v))
+            .collect::<IndexMap<_, _>>()
+            .into_iter(),
+        Level::Sequence(seq) => seq
+            .into_iter()
+            .enumerate()
+            .map(|(k, v)| (Cow::Owned(k.to_string()), v))
+            .collect::<IndexMap<_, _>>()
+            .into_iter(),
+        Level::Invalid(e) => return Err(de::Error::custom(e)),
+        _ => IndexMap::new().into_iter(),
+    };
+    Ok(QsDeserializer {
+        iter,
+        value: None,
+        config,
+    })
+}
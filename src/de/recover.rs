@@ -0,0 +1,267 @@
+//! Deserializing the top-level map while keeping track of which key failed,
+//! so that deserialization can be retried with an empty value in its place.
+
+use super::*;
+
+use serde::de;
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+
+/// A top-level deserializer which records the key whose value failed to
+/// deserialize, and substitutes [`DefaultDeserializer`] for the values of
+/// keys which previously failed.
+pub(crate) struct RecoveringDeserializer<'a, 'b> {
+    iter: IntoIter<Cow<'a, str>, Level<'a>>,
+    value: Option<Level<'a>>,
+    key: Option<String>,
+    config: Config,
+    /// Keys which are deserialized as their default value.
+    defaulted: &'b BTreeSet<String>,
+    /// The key of the value which failed to deserialize, if any.
+    failed: &'b RefCell<Option<String>>,
+}
+
+impl<'a, 'b> RecoveringDeserializer<'a, 'b> {
+    pub(crate) fn new(
+        map: IndexMap<Cow<'a, str>, Level<'a>>,
+        config: Config,
+        defaulted: &'b BTreeSet<String>,
+        failed: &'b RefCell<Option<String>>,
+    ) -> Self {
+        RecoveringDeserializer {
+            iter: map.into_iter(),
+            value: None,
+            key: None,
+            config,
+            defaulted,
+            failed,
+        }
+    }
+
+    fn into_qs_deserializer(self) -> QsDeserializer<'a> {
+        QsDeserializer::with_map(self.iter.collect(), self.config)
+    }
+}
+
+/// Only maps and structs are deserialized key by key; anything else, such as
+/// a top-level sequence, is left to `QsDeserializer`.
+macro_rules! forward_to_qs_deserializer {
+    ($($method:ident($($arg:ident: $ty:ty),*),)*) => {
+        $(
+            fn $method<V>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value>
+            where
+                V: de::Visitor<'de>,
+            {
+                self.into_qs_deserializer().$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for RecoveringDeserializer<'de, '_> {
+    type Error = Error;
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_map(self)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_map(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_qs_deserializer! {
+        deserialize_any(),
+        deserialize_bool(),
+        deserialize_i8(),
+        deserialize_i16(),
+        deserialize_i32(),
+        deserialize_i64(),
+        deserialize_u8(),
+        deserialize_u16(),
+        deserialize_u32(),
+        deserialize_u64(),
+        deserialize_f32(),
+        deserialize_f64(),
+        deserialize_char(),
+        deserialize_str(),
+        deserialize_string(),
+        deserialize_bytes(),
+        deserialize_byte_buf(),
+        deserialize_option(),
+        deserialize_unit(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_seq(),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]),
+        deserialize_identifier(),
+        deserialize_ignored_any(),
+    }
+}
+
+impl<'de> de::MapAccess<'de> for RecoveringDeserializer<'de, '_> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        if let Some((key, value)) = self.iter.next() {
+            self.key = Some(key.clone().into_owned());
+            self.value = Some(value);
+            seed.deserialize(ParsableStringDeserializer(key, self.config))
+                .map(Some)
+                .map_err(|e| {
+                    *self.failed.borrow_mut() = self.key.take();
+                    e
+                })
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let value = match self.value.take() {
+            Some(value) => value,
+            None => {
+                return Err(de::Error::custom(
+                    "Somehow the map was empty after a non-empty key was returned",
+                ))
+            }
+        };
+        let result = match self.key {
+            Some(ref key) if self.defaulted.contains(key) => seed.deserialize(DefaultDeserializer),
+            _ => seed.deserialize(LevelDeserializer(value, self.config)),
+        };
+        result.map_err(|e| {
+            *self.failed.borrow_mut() = self.key.take();
+            e
+        })
+    }
+}
+
+/// Deserializes the "empty" value of the requested type: zero, `false`, an
+/// empty string, `None`, or an empty sequence or map.
+pub(crate) struct DefaultDeserializer;
+
+impl<'de> de::IntoDeserializer<'de, Error> for DefaultDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! deserialize_zero {
+    ($($meth:ident => $visit:ident($zero:expr),)*) => {
+        $(
+            fn $meth<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: de::Visitor<'de>,
+            {
+                visitor.$visit($zero)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for DefaultDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    deserialize_zero! {
+        deserialize_bool => visit_bool(false),
+        deserialize_u8 => visit_u8(0),
+        deserialize_u16 => visit_u16(0),
+        deserialize_u32 => visit_u32(0),
+        deserialize_u64 => visit_u64(0),
+        deserialize_i8 => visit_i8(0),
+        deserialize_i16 => visit_i16(0),
+        deserialize_i32 => visit_i32(0),
+        deserialize_i64 => visit_i64(0),
+        deserialize_f32 => visit_f32(0.0),
+        deserialize_f64 => visit_f64(0.0),
+        deserialize_str => visit_str(""),
+        deserialize_string => visit_str(""),
+        deserialize_bytes => visit_bytes(&[]),
+        deserialize_byte_buf => visit_bytes(&[]),
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_none()
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_seq(de::value::SeqDeserializer::new(std::iter::empty::<()>()))
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_map(de::value::MapDeserializer::new(
+            std::iter::empty::<((), ())>(),
+        ))
+    }
+
+    /// Every field is present, with its own empty value.
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_map(de::value::MapDeserializer::new(
+            fields.iter().map(|&field| (field, DefaultDeserializer)),
+        ))
+    }
+
+    serde::forward_to_deserialize_any! {
+        char unit unit_struct tuple tuple_struct enum identifier ignored_any
+    }
+}
@@ -0,0 +1,86 @@
+//! Walking a parsed [`Level`] tree with a [`LevelVisitor`].
+
+use super::Level;
+
+/// Callbacks invoked by [`traverse`] for each node of a [`Level`] tree.
+///
+/// Every `key` is the full path of the node in bracket notation, e.g.
+/// `a[b][0]`, without any percent-encoding. All methods do nothing by
+/// default, so only the relevant ones need to be implemented.
+pub trait LevelVisitor {
+    /// Called for each flat value.
+    fn visit_flat(&mut self, key: &str, value: &str) {
+        let _ = (key, value);
+    }
+
+    /// Called before visiting the entries of a map.
+    fn visit_nested_start(&mut self, key: &str) {
+        let _ = key;
+    }
+
+    /// Called after visiting the entries of a map.
+    fn visit_nested_end(&mut self, key: &str) {
+        let _ = key;
+    }
+
+    /// Called before visiting element `index` of the sequence at `key`.
+    fn visit_seq_element(&mut self, key: &str, index: usize) {
+        let _ = (key, index);
+    }
+}
+
+/// Drives `visitor` over `level` and its children, depth first.
+///
+/// Map entries are visited in key order and sequence elements in index
+/// order. An empty `key` denotes the root, whose children are visited with
+/// their bare keys. Levels holding no value, such as those which failed to
+/// parse, are skipped.
+///
+/// ```
+/// use serde_qs::{traverse, LevelVisitor};
+///
+/// struct Keys(Vec<String>);
+///
+/// impl LevelVisitor for Keys {
+///     fn visit_flat(&mut self, key: &str, _value: &str) {
+///         self.0.push(key.to_owned());
+///     }
+/// }
+///
+/// let map = serde_qs::parse_top_level(b"a[b]=1&a[c][]=2&a[c][]=3").unwrap();
+/// let mut keys = Keys(Vec::new());
+/// traverse(&map["a"], "a", &mut keys);
+/// assert_eq!(keys.0, ["a[b]", "a[c][0]", "a[c][1]"]);
+/// ```
+pub fn traverse<V: LevelVisitor + ?Sized>(level: &Level<'_>, key: &str, visitor: &mut V) {
+    let child_key = |child: &str| {
+        if key.is_empty() {
+            child.to_owned()
+        } else {
+            format!("{}[{}]", key, child)
+        }
+    };
+    match *level {
+        Level::Nested(ref map) => {
+            visitor.visit_nested_start(key);
+            for (child, value) in map {
+                traverse(value, &child_key(child), visitor);
+            }
+            visitor.visit_nested_end(key);
+        }
+        Level::OrderedSeq(ref map) => {
+            for (&index, value) in map {
+                visitor.visit_seq_element(key, index);
+                traverse(value, &child_key(&index.to_string()), visitor);
+            }
+        }
+        Level::Sequence(ref seq) => {
+            for (index, value) in seq.iter().enumerate() {
+                visitor.visit_seq_element(key, index);
+                traverse(value, &child_key(&index.to_string()), visitor);
+            }
+        }
+        Level::Flat(ref value) => visitor.visit_flat(key, value),
+        Level::Invalid(_) | Level::Uninitialised => {}
+    }
+}
@@ -0,0 +1,76 @@
+//! Implementations of `schemars::JsonSchema`, enabled by the `schemars`
+//! feature, for documenting querystrings in e.g. OpenAPI specifications.
+//!
+//! Extractors describe the same schema as the type they wrap, and
+//! [`QsValue`] is any string, object or array.
+
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{Schema, SchemaObject, SubschemaValidation};
+use schemars::JsonSchema;
+
+use crate::QsValue;
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+#[cfg(any(feature = "actix4", feature = "actix3", feature = "axum"))]
+macro_rules! transparent_json_schema {
+    ($($ty:ty,)*) => {
+        $(
+            impl<T: JsonSchema> JsonSchema for $ty {
+                fn is_referenceable() -> bool {
+                    false
+                }
+
+                fn schema_name() -> String {
+                    T::schema_name()
+                }
+
+                fn schema_id() -> Cow<'static, str> {
+                    T::schema_id()
+                }
+
+                fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+                    T::json_schema(gen)
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(any(feature = "actix4", feature = "actix3"))]
+transparent_json_schema! {
+    crate::actix::QsQuery<T>,
+    crate::actix::QsForm<T>,
+}
+
+#[cfg(feature = "axum")]
+transparent_json_schema! {
+    crate::axum::QsQuery<T>,
+    crate::axum::QsForm<T>,
+}
+
+impl JsonSchema for QsValue {
+    fn schema_name() -> String {
+        "QsValue".to_owned()
+    }
+
+    fn schema_id() -> Cow<'static, str> {
+        Cow::Borrowed("serde_qs::QsValue")
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            subschemas: Some(Box::new(SubschemaValidation {
+                any_of: Some(vec![
+                    gen.subschema_for::<String>(),
+                    gen.subschema_for::<BTreeMap<String, QsValue>>(),
+                    gen.subschema_for::<Vec<QsValue>>(),
+                ]),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
@@ -22,9 +22,61 @@
 //! However, after the top level you should find all supported types can be
 //! de/serialized.
 //!
+//! As an exception, serializing a top-level primitive writes just the encoded
+//! value, without a key. `Some(value)` is written as `value` is, and `None` as
+//! an empty string:
+//!
+//! ```
+//! assert_eq!(serde_qs::to_string(&true).unwrap(), "true");
+//! assert_eq!(serde_qs::to_string(&Some("a b")).unwrap(), "a+b");
+//! assert_eq!(serde_qs::to_string(&None::<String>).unwrap(), "");
+//! ```
+//!
 //! Note that integer keys are reserved for array indices. That is, a string of
 //! the form `a[0]=1&a[1]=3` will deserialize to the ordered sequence `a =
-//! [1,3]`.
+//! [1,3]`. The same applies to any other sequence type, such as `VecDeque`,
+//! `LinkedList`, or `SmallVec` and `ArrayVec` with their `serde` features
+//! enabled; no feature of `serde_qs` is needed for these.
+//!
+//! Newtype structs defer to their inner value, so `struct UserId(String)`
+//! used as a field `id` is written as `id=abc`. The same holds for types
+//! marked `#[serde(transparent)]`, which may also wrap a struct with a single
+//! field:
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate serde_derive;
+//! # extern crate serde_qs;
+//! #[derive(Debug, PartialEq, Deserialize, Serialize)]
+//! #[serde(transparent)]
+//! struct UserId {
+//!     inner: String,
+//! }
+//!
+//! #[derive(Debug, PartialEq, Deserialize, Serialize)]
+//! struct Query {
+//!     id: UserId,
+//! }
+//!
+//! # fn main() {
+//! let query = Query { id: UserId { inner: "abc".to_owned() } };
+//! assert_eq!(serde_qs::to_string(&query).unwrap(), "id=abc");
+//! assert_eq!(serde_qs::from_str::<Query>("id=abc").unwrap(), query);
+//! # }
+//! ```
+//!
+//! Fields of type `&str` borrow from the input, without allocating, as long
+//! as their values need no percent-decoding and contain no `+`. Otherwise
+//! deserialization fails, so use `String` for values which may be encoded,
+//! or `Cow<str>` marked `#[serde(borrow)]`, which borrows when it can and
+//! only allocates for values which are decoded.
+//!
+//! With the `base64` feature, byte buffers (e.g. `Vec<u8>` fields using
+//! `serde_bytes`) are deserialized from base64-encoded values, in either the
+//! standard or the URL-safe alphabet.
+//!
+//! With the `schemars` feature, the extractors and [`QsValue`] implement
+//! `schemars::JsonSchema`, describing the same schema as the wrapped type.
 //!
 //! ## Usage
 //!
@@ -72,6 +124,15 @@
 //! # }
 //! ```
 //!
+//! ### Key order
+//!
+//! Output is deterministic: structs are serialized in the order their
+//! fields are declared, and maps in their own iteration order, which for a
+//! `HashMap` is arbitrary, so use a `BTreeMap` or [`to_sorted_string`] for
+//! stable output. When deserializing, the keys at each level are visited in
+//! sorted order, or in the order they first appear in the querystring with
+//! [`Config::key_order`], and sequence elements in index order.
+//!
 //! ## Strict vs Non-Strict modes
 //!
 //! `serde_qs` supports two operating modes, which can be specified using
@@ -109,6 +170,8 @@
 //! `serde_qs` works in strict mode (the default). Whereas in other cases it
 //! can be useful to just replace such data with the unicode replacement
 //! character (� `U+FFFD`), which is how `serde_qs` works in non-strict mode.
+//! This can also be chosen independently of the handling of square brackets,
+//! using [`Config::strict_utf8`](struct.Config.html#method.strict_utf8).
 //!
 //! ## Flatten workaround
 //!
@@ -154,6 +217,11 @@
 //! }
 //! ```
 //!
+//! Flattened structs whose fields are all strings need no workaround.
+//! Alternatively, [`from_str_number_strings`] presents values which look
+//! like numbers or booleans as such, at the cost of string fields within
+//! the flattened struct rejecting such values.
+//!
 //! ## Use with `actix_web` extractors
 //!
 //! The `actix4`, `actix3` or `actix2` features enable the use of `serde_qs::actix::QsQuery`, which
@@ -169,6 +237,11 @@
 //! Support for `actix-web 3.0` is available via the `actix3` feature.
 //! Support for `actix-web 2.0` is available via the `actix2` feature.
 //!
+//! ## Reading asynchronously
+//!
+//! The `tokio` feature enables `serde_qs::from_async_reader`, which reads a
+//! querystring from a `tokio::io::AsyncRead` such as a request body.
+//!
 //! ## Use with `warp` filters
 //!
 //! The `warp` feature enables the use of `serde_qs::warp::query()`, which
@@ -183,9 +256,6 @@
 //! ```
 //!
 
-#[macro_use]
-extern crate serde;
-
 #[cfg(any(feature = "actix4", feature = "actix3"))]
 pub mod actix;
 
@@ -208,16 +278,53 @@ compile_error!(
 
 mod de;
 mod error;
+#[cfg(feature = "schemars")]
+mod json_schema;
+pub mod nested;
+pub mod prelude;
+mod query_string;
 mod ser;
+pub mod testing;
+pub mod unix_timestamp;
 pub(crate) mod utils;
+mod value;
 
+#[cfg(feature = "tokio")]
+#[doc(inline)]
+pub use de::{from_async_reader, from_async_reader_with_config};
 #[doc(inline)]
-pub use de::{from_bytes, from_str};
+pub use de::{
+    from_bytes, from_bytes_skip_encoding, from_bytes_with_bom_stripping, from_bytes_with_config,
+    from_pairs, from_reader, from_reader_with_config, from_str, from_str_all_str,
+    from_str_collect_errors, from_str_lenient, from_str_number_strings, from_str_seeded,
+    from_str_strict_utf8, from_str_with_base, from_str_with_config,
+};
 #[doc(inline)]
-pub use de::{Config, QsDeserializer as Deserializer};
+pub use de::{
+    parse_top_level, traverse, Config, ErrorPolicy, KeyOrder, Level, LevelVisitor,
+    QsDeserializer as Deserializer, RepeatedKeys,
+};
 pub use error::Error;
+pub use percent_encoding::AsciiSet;
+pub use query_string::QueryString;
 #[doc(inline)]
-pub use ser::{to_string, to_writer, Serializer};
+pub use ser::{
+    diff_keys, filter_keys, replace, to_bytes, to_query_string_for_signing, to_sorted_string,
+    to_string, to_string_with_timestamp, to_string_with_timestamp_key, to_writer, ArrayFormat,
+    Serializer,
+};
+pub use utils::{build_key_path, parse_key_path, QS_ENCODE_SET};
+pub use value::QsValue;
+
+/// The media type of an HTML form body, which is a querystring.
+pub const FORM_CONTENT_TYPE: &str = "application/x-www-form-urlencoded";
+
+/// [`FORM_CONTENT_TYPE`] with the UTF-8 charset parameter, as produced by
+/// [`to_string`].
+pub const FORM_CONTENT_TYPE_WITH_CHARSET: &str = "application/x-www-form-urlencoded; charset=utf-8";
+
+#[doc(hidden)]
+pub use ser::sort_pairs as __sort_pairs;
 
 #[cfg(feature = "axum")]
 pub mod axum;
@@ -0,0 +1,54 @@
+//! Functions for (de)serializing a single field as a querystring, for use
+//! with `#[serde(with = "serde_qs::nested")]`.
+//!
+//! This lets a field of a type serialized with another format, such as JSON
+//! or TOML, hold its value in bracket notation:
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate serde_derive;
+//! # extern crate serde_json;
+//! # extern crate serde_qs;
+//! #[derive(Debug, Deserialize, PartialEq, Serialize)]
+//! struct Filter {
+//!     tags: Vec<String>,
+//!     min: u32,
+//! }
+//!
+//! #[derive(Debug, Deserialize, PartialEq, Serialize)]
+//! struct Request {
+//!     #[serde(with = "serde_qs::nested")]
+//!     filter: Filter,
+//! }
+//!
+//! # fn main(){
+//! let request = Request {
+//!     filter: Filter { tags: vec!["a".to_owned()], min: 3 },
+//! };
+//! let json = r#"{"filter":"tags[0]=a&min=3"}"#;
+//! assert_eq!(serde_json::to_string(&request).unwrap(), json);
+//! assert_eq!(serde_json::from_str::<Request>(json).unwrap(), request);
+//! # }
+//! ```
+
+use serde::{de, ser};
+
+/// Serializes `value` as a querystring string.
+pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: ser::Serialize,
+    S: ser::Serializer,
+{
+    let qs = crate::to_string(value).map_err(ser::Error::custom)?;
+    serializer.serialize_str(&qs)
+}
+
+/// Deserializes a value from a querystring string.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: de::DeserializeOwned,
+    D: de::Deserializer<'de>,
+{
+    let qs = <String as de::Deserialize>::deserialize(deserializer)?;
+    crate::from_str(&qs).map_err(de::Error::custom)
+}
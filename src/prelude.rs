@@ -0,0 +1,11 @@
+//! The most commonly used items, for glob importing.
+//!
+//! ```
+//! use serde_qs::prelude::*;
+//! use std::collections::HashMap;
+//!
+//! let map: HashMap<String, String> = from_str("a=1").unwrap();
+//! assert_eq!(to_string(&map).unwrap(), "a=1");
+//! ```
+
+pub use crate::{from_bytes, from_str, to_string, Config, Error};
@@ -0,0 +1,128 @@
+//! An owned, serialized querystring.
+
+use serde::ser;
+
+use crate::error::Result;
+use crate::ser::{decode, pair_key};
+use crate::utils::split_key;
+
+use std::fmt;
+use std::ops::{Add, AddAssign};
+
+/// A querystring produced by serializing a value.
+///
+/// A blanket `TryFrom<T: Serialize>` implementation would conflict with the
+/// standard library's reflexive `TryFrom`, so values are converted using
+/// [`QueryString::from_value`] instead.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// # extern crate serde_qs;
+/// use serde_qs::QueryString;
+///
+/// #[derive(Serialize)]
+/// struct Query {
+///     page: u32,
+///     tags: Vec<&'static str>,
+/// }
+///
+/// # fn main() -> Result<(), serde_qs::Error> {
+/// let qs = QueryString::from_value(&Query { page: 2, tags: vec!["a"] })?;
+/// assert_eq!(qs.as_str(), "page=2&tags[0]=a");
+/// assert_eq!(format!("/search?{}", qs), "/search?page=2&tags[0]=a");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Further pairs can be merged in using `+` or `+=`. Pairs already present
+/// whose key overlaps with a new key, such as `a` or `a[b][c]` for `a[b]`,
+/// are removed, and the new pairs are appended. A key such as `a[]` replaces
+/// the whole sequence `a`:
+///
+/// ```
+/// use serde_qs::QueryString;
+///
+/// let qs = QueryString::default() + "page=1&sort=name";
+/// assert_eq!((qs + "&page=2").as_str(), "sort=name&page=2");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct QueryString(String);
+
+impl QueryString {
+    /// Serializes `value` into a `QueryString`, as with [`to_string`].
+    ///
+    /// [`to_string`]: crate::to_string
+    pub fn from_value<T: ser::Serialize>(value: &T) -> Result<Self> {
+        crate::to_string(value).map(QueryString)
+    }
+
+    /// Returns the querystring as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Consumes the `QueryString`, returning the underlying `String`.
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl fmt::Display for QueryString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for QueryString {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<QueryString> for String {
+    fn from(qs: QueryString) -> String {
+        qs.0
+    }
+}
+
+impl AddAssign<&str> for QueryString {
+    fn add_assign(&mut self, rhs: &str) {
+        // Unindexed sequence segments such as `a[]` stand for the whole
+        // sequence, so the path is cut short at the first of them.
+        fn path(pair: &str) -> Vec<String> {
+            let key = decode(pair_key(pair));
+            let mut path = split_key(&key);
+            if let Some(end) = path.iter().skip(1).position(|s| s.is_empty()) {
+                path.truncate(end + 1);
+            }
+            path.into_iter().map(str::to_owned).collect()
+        }
+
+        let rhs = rhs.trim_start_matches(['?', '&']);
+        let new_pairs = rhs.split('&').filter(|pair| !pair.is_empty());
+        let new_paths = new_pairs.clone().map(path).collect::<Vec<_>>();
+        let overlaps = |pair: &str| {
+            let path = path(pair);
+            new_paths
+                .iter()
+                .any(|new_path| path.starts_with(new_path) || new_path.starts_with(&path))
+        };
+        let pairs = self
+            .0
+            .split('&')
+            .filter(|pair| !pair.is_empty() && !overlaps(pair))
+            .chain(new_pairs)
+            .collect::<Vec<_>>();
+        self.0 = pairs.join("&");
+    }
+}
+
+impl Add<&str> for QueryString {
+    type Output = QueryString;
+
+    fn add(mut self, rhs: &str) -> QueryString {
+        self += rhs;
+        self
+    }
+}
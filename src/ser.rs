@@ -1,21 +1,35 @@
 //! Serialization support for querystrings.
 
-use percent_encoding::percent_encode;
+use percent_encoding::{
+    percent_decode_str, percent_encode, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC,
+};
 use serde::ser;
 
+use crate::de::Config;
 use crate::error::*;
 use crate::utils::*;
 
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt::Display;
 use std::io::Write;
 use std::str;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Serializes a value into a querystring.
 ///
+/// Values nested more than 128 levels deep are rejected, which guards against
+/// runaway recursive types. Use [`Config::serialize_depth_limit`] to pick a
+/// different limit.
+///
+/// The output depends only on what the value serializes: the same value
+/// always gives the same querystring. Maps are written in their iteration
+/// order though, so two equal `HashMap`s may give different querystrings;
+/// use [`to_sorted_string`] when those must match too.
+///
 /// ```
 /// # #[macro_use]
 /// # extern crate serde_derive;
@@ -46,8 +60,37 @@ pub fn to_string<T: ser::Serialize>(input: &T) -> Result<String> {
     String::from_utf8(buffer).map_err(Error::from)
 }
 
+/// Serializes a value into a querystring, returned as bytes.
+///
+/// This is equivalent to [`to_string`], but is convenient for e.g. request
+/// bodies which expect a `Vec<u8>`.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// # extern crate serde_qs;
+/// #[derive(Serialize)]
+/// struct Query {
+///     name: String,
+///     age: u8,
+/// }
+///
+/// # fn main(){
+/// let q = Query { name: "Alice".to_owned(), age: 24 };
+/// assert_eq!(serde_qs::to_bytes(&q).unwrap(), b"name=Alice&age=24");
+/// # }
+/// ```
+pub fn to_bytes<T: ser::Serialize>(input: &T) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    input.serialize(&mut Serializer::new(&mut buffer))?;
+    Ok(buffer)
+}
+
 /// Serializes a value into a generic writer object.
 ///
+/// Each pair is written as soon as it is serialized, so the querystring is
+/// never held in memory as a whole.
+///
 /// ```
 /// # #[macro_use]
 /// # extern crate serde_derive;
@@ -77,20 +120,420 @@ pub fn to_writer<T: ser::Serialize, W: Write>(input: &T, writer: &mut W) -> Resu
     input.serialize(&mut Serializer::new(writer))
 }
 
+/// Serializes a value into a querystring, with the keys sorted at every
+/// level of nesting.
+///
+/// Unlike [`to_string`], the output does not depend on the iteration order of
+/// the maps being serialized (e.g. `HashMap`), which makes it suitable for
+/// comparing querystrings in tests or using them as cache keys. Sequence
+/// indices are sorted numerically, so element order is preserved.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// # extern crate serde_qs;
+/// use std::collections::HashMap;
+///
+/// #[derive(Serialize)]
+/// struct Query {
+///     b: u8,
+///     a: HashMap<&'static str, u8>,
+/// }
+///
+/// # fn main(){
+/// let q = Query {
+///     b: 3,
+///     a: vec![("y", 2), ("x", 1)].into_iter().collect(),
+/// };
+///
+/// assert_eq!(
+///     serde_qs::to_sorted_string(&q).unwrap(),
+///     "a[x]=1&a[y]=2&b=3");
+/// # }
+/// ```
+pub fn to_sorted_string<T: ser::Serialize>(input: &T) -> Result<String> {
+    to_string(input).map(|s| sort_pairs(&s))
+}
+
+/// The characters encoded by [`to_query_string_for_signing`]: everything
+/// except the unreserved characters of RFC 3986.
+const UNRESERVED_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Serializes a value into the canonical querystring used to sign requests,
+/// e.g. by AWS Signature Version 4 and OAuth 1.0a.
+///
+/// Keys and values are percent-encoded except for the unreserved characters
+/// `A-Za-z0-9-._~`, so a space is written as `%20` rather than `+`, and
+/// brackets as `%5B` and `%5D`. The pairs are then sorted by encoded key,
+/// and pairs sharing a key by encoded value.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// # extern crate serde_qs;
+/// #[derive(Serialize)]
+/// struct Query {
+///     prefix: String,
+///     filter: Vec<String>,
+///     #[serde(rename = "Action")]
+///     action: String,
+/// }
+///
+/// # fn main(){
+/// let q = Query {
+///     prefix: "a b~".to_owned(),
+///     filter: vec!["x*".to_owned()],
+///     action: "List".to_owned(),
+/// };
+/// assert_eq!(
+///     serde_qs::to_query_string_for_signing(&q).unwrap(),
+///     "Action=List&filter%5B0%5D=x%2A&prefix=a%20b~");
+/// # }
+/// ```
+pub fn to_query_string_for_signing<T: ser::Serialize>(input: &T) -> Result<String> {
+    Config::default().serialize_string_for_signing(input)
+}
+
+/// Serializes a value into a querystring, followed by a `_ts` parameter
+/// holding the current Unix time in milliseconds.
+///
+/// This is useful for cache-busting requests. See
+/// [`to_string_with_timestamp_key`] to use a key other than `_ts`.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// # extern crate serde_qs;
+/// #[derive(Serialize)]
+/// struct Query {
+///     page: u32,
+/// }
+///
+/// # fn main(){
+/// let qs = serde_qs::to_string_with_timestamp(&Query { page: 2 }).unwrap();
+/// assert!(qs.starts_with("page=2&_ts="));
+/// # }
+/// ```
+pub fn to_string_with_timestamp<T: ser::Serialize>(input: &T) -> Result<String> {
+    to_string_with_timestamp_key(input, "_ts")
+}
+
+/// Serializes a value into a querystring, followed by a `key` parameter
+/// holding the current Unix time in milliseconds.
+pub fn to_string_with_timestamp_key<T: ser::Serialize>(input: &T, key: &str) -> Result<String> {
+    let mut output = to_string(input)?;
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or(0);
+    if !output.is_empty() {
+        output.push('&');
+    }
+    output.extend(percent_encode(key.as_bytes(), QS_ENCODE_SET).map(replace_space));
+    output.push('=');
+    output.push_str(&millis.to_string());
+    Ok(output)
+}
+
+/// Replaces the value of `field` in the querystring `base` with `value`.
+///
+/// Nested fields are addressed using dot notation, so `filter.min_price`
+/// refers to the key `filter[min_price]`. Every pair at or below `field` is
+/// removed and the serialized `value` is inserted in place of the first of
+/// them, or appended if `field` was not present. All other pairs are kept
+/// as they are.
+///
+/// ```
+/// assert_eq!(
+///     serde_qs::replace("page=1&sort=name", "page", &2).unwrap(),
+///     "page=2&sort=name");
+/// assert_eq!(
+///     serde_qs::replace("filter[min_price]=5&page=1", "filter.min_price", &100).unwrap(),
+///     "filter[min_price]=100&page=1");
+/// ```
+pub fn replace<T: ser::Serialize>(base: &str, field: &str, value: &T) -> Result<String> {
+    let path = field.split('.').collect::<Vec<_>>();
+
+    let mut buffer = Vec::new();
+    {
+        let mut serializer = Serializer::new(&mut buffer);
+        let mut serializer = serializer.as_qs_serializer();
+        for segment in &path {
+            serializer.extend_key(segment)?;
+        }
+        value.serialize(serializer)?;
+    }
+    let replacement = String::from_utf8(buffer)?;
+
+    let mut pairs = Vec::new();
+    let mut inserted = false;
+    for pair in base.split('&').filter(|pair| !pair.is_empty()) {
+        let key = decode(pair_key(pair));
+        if split_key(&key).starts_with(&path) {
+            if !inserted {
+                pairs.push(replacement.as_str());
+                inserted = true;
+            }
+        } else {
+            pairs.push(pair);
+        }
+    }
+    if !inserted {
+        pairs.push(replacement.as_str());
+    }
+    pairs.retain(|pair| !pair.is_empty());
+    Ok(pairs.join("&"))
+}
+
+/// Serializes a value into a querystring, keeping only the pairs whose key
+/// passes `predicate`.
+///
+/// The predicate is given the fully-qualified, decoded key of each pair,
+/// such as `address[city]` or `tags[0]`.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// # extern crate serde_qs;
+/// #[derive(Serialize)]
+/// struct Query {
+///     name: String,
+///     internal_id: u32,
+/// }
+///
+/// # fn main(){
+/// let q = Query { name: "Alice".to_owned(), internal_id: 7 };
+/// assert_eq!(
+///     serde_qs::filter_keys(&q, |key| !key.starts_with("internal_")).unwrap(),
+///     "name=Alice");
+/// # }
+/// ```
+pub fn filter_keys<T, F>(input: &T, predicate: F) -> Result<String>
+where
+    T: ser::Serialize,
+    F: Fn(&str) -> bool,
+{
+    let output = to_string(input)?;
+    Ok(output
+        .split('&')
+        .filter(|pair| !pair.is_empty() && predicate(&decode(pair_key(pair))))
+        .collect::<Vec<_>>()
+        .join("&"))
+}
+
+/// Compares the pairs of two querystrings by their decoded keys, returning
+/// the keys which were `(added, removed, changed)` going from `a` to `b`.
+///
+/// Keys are compared in full, such as `address[city]`, and each list is
+/// sorted by key. A key which is repeated, e.g. `a[]=1&a[]=2`, has changed
+/// if its sequence of values has.
+///
+/// ```
+/// let (added, removed, changed) =
+///     serde_qs::diff_keys("a=1&b=2&c[d]=3", "a=1&b=4&e=5");
+/// assert_eq!(added, ["e"]);
+/// assert_eq!(removed, ["c[d]"]);
+/// assert_eq!(changed, ["b"]);
+/// ```
+pub fn diff_keys(a: &str, b: &str) -> (Vec<String>, Vec<String>, Vec<String>) {
+    fn values_by_key(input: &str) -> HashMap<String, Vec<String>> {
+        let mut values: HashMap<_, Vec<_>> = HashMap::new();
+        for pair in input.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            values.entry(decode(key)).or_default().push(decode(value));
+        }
+        values
+    }
+
+    let a = values_by_key(a);
+    let b = values_by_key(b);
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    for (key, values) in &b {
+        match a.get(key) {
+            None => added.push(key.clone()),
+            Some(old) if old != values => changed.push(key.clone()),
+            Some(_) => {}
+        }
+    }
+    let mut removed = a
+        .keys()
+        .filter(|key| !b.contains_key(*key))
+        .cloned()
+        .collect::<Vec<_>>();
+    for keys in [&mut added, &mut removed, &mut changed] {
+        keys.sort_by(|a, b| compare_keys(a, b));
+    }
+    (added, removed, changed)
+}
+
+pub(crate) fn decode(key: &str) -> String {
+    percent_decode_str(&key.replace('+', " "))
+        .decode_utf8_lossy()
+        .into_owned()
+}
+
+/// Asserts that a value serializes to the `expected` querystring, ignoring
+/// the order of the pairs.
+///
+/// Both sides are sorted by key as with [`to_sorted_string`] before being
+/// compared, so the assertion does not depend on field or map iteration
+/// order. Like `assert_eq!`, a custom panic message may follow.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// # #[macro_use]
+/// # extern crate serde_qs;
+/// #[derive(Serialize)]
+/// struct Query {
+///     name: String,
+///     age: u8,
+/// }
+///
+/// # fn main(){
+/// let q = Query { name: "Alice".to_owned(), age: 24 };
+/// assert_eq_qs!(q, "age=24&name=Alice");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! assert_eq_qs {
+    ($value:expr, $expected:expr $(,)?) => {
+        match $crate::to_sorted_string(&$value) {
+            Ok(actual) => assert_eq!(
+                actual,
+                $crate::__sort_pairs(&$expected),
+                "querystrings differ (pairs sorted by key)"
+            ),
+            Err(e) => panic!("failed to serialize value: {}", e),
+        }
+    };
+    ($value:expr, $expected:expr, $($arg:tt)+) => {
+        match $crate::to_sorted_string(&$value) {
+            Ok(actual) => assert_eq!(actual, $crate::__sort_pairs(&$expected), $($arg)+),
+            Err(e) => panic!("failed to serialize value: {}", e),
+        }
+    };
+}
+
+/// Sorts the pairs of an encoded querystring by key.
+pub fn sort_pairs(input: &str) -> String {
+    let mut pairs = input
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .collect::<Vec<_>>();
+    // `sort_by` is stable, so pairs sharing a key keep their relative order.
+    pairs.sort_by(|a, b| compare_keys(pair_key(a), pair_key(b)));
+    pairs.join("&")
+}
+
+pub(crate) fn pair_key(pair: &str) -> &str {
+    pair.split('=').next().unwrap_or(pair)
+}
+
+impl Config {
+    /// Serializes a value into a querystring, returning an error if the
+    /// value is nested deeper than `serialize_depth_limit`.
+    ///
+    /// ```
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// # extern crate serde_qs;
+    /// #[derive(Serialize)]
+    /// struct Query {
+    ///     name: String,
+    ///     tags: Vec<String>,
+    /// }
+    ///
+    /// # fn main(){
+    /// let q = Query {
+    ///     name: "Alice".to_owned(),
+    ///     tags: vec!["a".to_owned()],
+    /// };
+    ///
+    /// let config = serde_qs::Config::default().include_leading_question_mark(true);
+    /// assert_eq!(
+    ///     config.serialize_string(&q).unwrap(),
+    ///     "?name=Alice&tags[0]=a");
+    /// # }
+    /// ```
+    pub fn serialize_string<T: ser::Serialize>(&self, input: &T) -> Result<String> {
+        let mut buffer = Vec::new();
+        self.serialize_to_writer(input, &mut buffer)?;
+        String::from_utf8(buffer).map_err(Error::from)
+    }
+
+    /// Serializes a value into a generic writer object, returning an error
+    /// if the value is nested deeper than `serialize_depth_limit`.
+    pub fn serialize_to_writer<T: ser::Serialize, W: Write>(
+        &self,
+        input: &T,
+        writer: &mut W,
+    ) -> Result<()> {
+        input.serialize(&mut Serializer::with_config(writer, *self))
+    }
+
+    /// Serializes a value into the canonical querystring used to sign
+    /// requests, as with [`to_query_string_for_signing`].
+    ///
+    /// Use `ArrayFormat::Repeat` where sequences are given as repeated keys.
+    pub fn serialize_string_for_signing<T: ser::Serialize>(&self, input: &T) -> Result<String> {
+        let output = self.serialize_string(input)?;
+        let output = output.strip_prefix('?').unwrap_or(&output);
+        let mut pairs = output
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                let encode =
+                    |s: &str| utf8_percent_encode(&decode(s), UNRESERVED_ENCODE_SET).to_string();
+                (encode(key), encode(value))
+            })
+            .collect::<Vec<_>>();
+        pairs.sort();
+        Ok(pairs
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join("&"))
+    }
+}
+
 pub struct Serializer<W: Write> {
     writer: W,
+    config: Config,
 }
 
 impl<W: Write> Serializer<W> {
     pub fn new(writer: W) -> Self {
-        Self { writer }
+        Self::with_config(writer, Config::default())
+    }
+
+    /// Creates a new `Serializer` which errors on values nested deeper than
+    /// the `serialize_depth_limit` of `config`.
+    pub fn with_config(writer: W, config: Config) -> Self {
+        Self { writer, config }
     }
 
-    fn as_qs_serializer(&mut self) -> QsSerializer<W> {
+    fn as_qs_serializer(&mut self) -> QsSerializer<'_, W> {
         QsSerializer {
             writer: &mut self.writer,
             first: Arc::new(AtomicBool::new(true)),
             key: None,
+            depth: 0,
+            max_depth: self.config.serialize_depth_limit,
+            encoding_set: self.config.encoding_set,
+            array_format: self.config.array_format,
+            unindexed: false,
+            prefix: if self.config.leading_question_mark {
+                "?"
+            } else {
+                ""
+            },
         }
     }
 }
@@ -111,10 +554,10 @@ macro_rules! serialize_as_string {
             }
         )*
     };
-    ($($ty:ty => $meth:ident,)*) => {
+    (Key $($ty:ty => $meth:ident,)*) => {
         $(
             fn $meth(self, v: $ty) -> Result<Self::Ok> {
-                Ok(v.to_string())
+                self.0.extend_key(&v.to_string())
             }
         )*
     };
@@ -245,6 +688,24 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
     }
 }
 
+/// How the elements of a sequence are keyed when serializing, set using
+/// [`Config::array_format`].
+///
+/// Only indexed keys can describe sequences of structs, maps or other
+/// sequences unambiguously, so serializing those with another format is an
+/// error. Sequences of primitives round-trip with any format, as repeated
+/// keys are collected into a sequence by default, see
+/// [`Config::repeated_keys`]. Top-level sequences are always indexed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArrayFormat {
+    /// `a[0]=1&a[1]=2`. This is the default.
+    Indexed,
+    /// `a[]=1&a[]=2`.
+    Brackets,
+    /// `a=1&a=2`.
+    Repeat,
+}
+
 /// A serializer for the querystring format.
 ///
 /// * Supported top-level inputs are structs and maps.
@@ -258,43 +719,87 @@ pub struct QsSerializer<'a, W: 'a + Write> {
     key: Option<Cow<'static, str>>,
     writer: &'a mut W,
     first: Arc<AtomicBool>,
+    /// Number of brackets in `key`.
+    depth: usize,
+    max_depth: usize,
+    encoding_set: &'static AsciiSet,
+    array_format: ArrayFormat,
+    /// Whether `key` is that of an element of an unindexed sequence, which
+    /// can only be a primitive value.
+    unindexed: bool,
+    /// Written before the first pair.
+    prefix: &'static str,
 }
 
 impl<'a, W: 'a + Write> QsSerializer<'a, W> {
-    fn extend_key(&mut self, newkey: &str) {
-        let newkey = percent_encode(newkey.as_bytes(), QS_ENCODE_SET)
-            .map(replace_space)
-            .collect::<String>();
+    fn extend_key(&mut self, newkey: &str) -> Result<()> {
+        let encoded = percent_encode(newkey.as_bytes(), self.encoding_set).map(replace_space);
+        // Build the key in place, rather than encoding `newkey` separately.
         let key = if let Some(ref key) = self.key {
-            format!("{}[{}]", key, newkey)
+            if self.depth >= self.max_depth {
+                return Err(Error::max_depth(self.max_depth));
+            }
+            self.depth += 1;
+            let mut extended = String::with_capacity(key.len() + newkey.len() + 2);
+            extended.push_str(key);
+            extended.push('[');
+            extended.extend(encoded);
+            extended.push(']');
+            extended
         } else {
-            newkey
+            encoded.collect()
         };
-        self.key = Some(Cow::Owned(key))
+        self.key = Some(Cow::Owned(key));
+        Ok(())
+    }
+
+    /// Errors if a struct, map or sequence is serialized as an element of an
+    /// unindexed sequence, which could not be deserialized again.
+    fn check_indexed(&self) -> Result<()> {
+        if self.unindexed {
+            let msg = "sequences of structs, maps or sequences can only be serialized with \
+                       `ArrayFormat::Indexed`";
+            return Err(Error::Custom(msg.into()));
+        }
+        Ok(())
     }
 
     fn write_value(&mut self, value: &[u8]) -> Result<()> {
+        let value = percent_encode(value, self.encoding_set)
+            .map(replace_space)
+            .collect::<String>();
+        let amp = !self.first.swap(false, Ordering::Relaxed);
         if let Some(ref key) = self.key {
-            let amp = !self.first.swap(false, Ordering::Relaxed);
             write!(
                 self.writer,
                 "{}{}={}",
-                if amp { "&" } else { "" },
+                if amp { "&" } else { self.prefix },
                 key,
-                percent_encode(value, QS_ENCODE_SET)
-                    .map(replace_space)
-                    .collect::<String>()
+                value
             )
             .map_err(Error::from)
         } else {
-            Err(Error::no_key())
+            // Only a top-level primitive has no key, so write it on its own.
+            write!(
+                self.writer,
+                "{}{}",
+                if amp { "&" } else { self.prefix },
+                value
+            )
+            .map_err(Error::from)
         }
     }
 
     fn write_unit(&mut self) -> Result<()> {
         let amp = !self.first.swap(false, Ordering::Relaxed);
         if let Some(ref key) = self.key {
-            write!(self.writer, "{}{}=", if amp { "&" } else { "" }, key,).map_err(Error::from)
+            write!(
+                self.writer,
+                "{}{}=",
+                if amp { "&" } else { self.prefix },
+                key,
+            )
+            .map_err(Error::from)
         } else if amp {
             write!(self.writer, "&").map_err(Error::from)
         } else {
@@ -309,6 +814,12 @@ impl<'a, W: 'a + Write> QsSerializer<'a, W> {
             key: other.key.clone(),
             writer: other.writer,
             first: other.first.clone(),
+            depth: other.depth,
+            max_depth: other.max_depth,
+            encoding_set: other.encoding_set,
+            array_format: other.array_format,
+            unindexed: false,
+            prefix: other.prefix,
         }
     }
 }
@@ -318,6 +829,13 @@ impl Error {
         let msg = "tried to serialize a value before serializing key";
         Error::Custom(msg.into())
     }
+
+    fn max_depth(max_depth: usize) -> Self {
+        Error::Custom(format!(
+            "value is nested deeper than the maximum depth of {}",
+            max_depth
+        ))
+    }
 }
 
 impl<'a, W: Write> ser::Serializer for QsSerializer<'a, W> {
@@ -384,7 +902,8 @@ impl<'a, W: Write> ser::Serializer for QsSerializer<'a, W> {
         variant: &'static str,
         value: &T,
     ) -> Result<Self::Ok> {
-        self.extend_key(variant);
+        self.check_indexed()?;
+        self.extend_key(variant)?;
         value.serialize(self)
     }
 
@@ -397,10 +916,12 @@ impl<'a, W: Write> ser::Serializer for QsSerializer<'a, W> {
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        self.check_indexed()?;
         Ok(QsSeq(self, 0))
     }
 
     fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        self.check_indexed()?;
         Ok(QsSeq(self, 0))
     }
 
@@ -409,6 +930,7 @@ impl<'a, W: Write> ser::Serializer for QsSerializer<'a, W> {
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
+        self.check_indexed()?;
         Ok(QsSeq(self, 0))
     }
 
@@ -419,15 +941,18 @@ impl<'a, W: Write> ser::Serializer for QsSerializer<'a, W> {
         variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        self.extend_key(variant);
+        self.check_indexed()?;
+        self.extend_key(variant)?;
         Ok(QsSeq(self, 0))
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        self.check_indexed()?;
         Ok(QsMap(self, None))
     }
 
     fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        self.check_indexed()?;
         Ok(self)
     }
 
@@ -438,7 +963,8 @@ impl<'a, W: Write> ser::Serializer for QsSerializer<'a, W> {
         variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        self.extend_key(variant);
+        self.check_indexed()?;
+        self.extend_key(variant)?;
         Ok(self)
     }
 }
@@ -455,8 +981,30 @@ impl ser::Error for Error {
 #[doc(hidden)]
 pub struct QsSeq<'a, W: 'a + Write>(QsSerializer<'a, W>, usize);
 
+/// The extended key of the next value, and its depth, are kept between
+/// `serialize_key` and `serialize_value`.
 #[doc(hidden)]
-pub struct QsMap<'a, W: 'a + Write>(QsSerializer<'a, W>, Option<Cow<'a, str>>);
+pub struct QsMap<'a, W: 'a + Write>(QsSerializer<'a, W>, Option<(Cow<'static, str>, usize)>);
+
+impl<W: Write> QsSeq<'_, W> {
+    /// Returns a serializer keyed for the next element, according to the
+    /// `ArrayFormat`.
+    fn next_element(&mut self) -> Result<QsSerializer<'_, W>> {
+        let index = self.1;
+        self.1 += 1;
+        let mut serializer = QsSerializer::new_from_ref(&mut self.0);
+        match serializer.array_format {
+            _ if serializer.key.is_none() => serializer.extend_key(&index.to_string())?,
+            ArrayFormat::Indexed => serializer.extend_key(&index.to_string())?,
+            ArrayFormat::Brackets => {
+                serializer.extend_key("")?;
+                serializer.unindexed = true;
+            }
+            ArrayFormat::Repeat => serializer.unindexed = true,
+        }
+        Ok(serializer)
+    }
+}
 
 impl<W: Write> ser::SerializeTuple for QsSeq<'_, W> {
     type Ok = ();
@@ -465,11 +1013,7 @@ impl<W: Write> ser::SerializeTuple for QsSeq<'_, W> {
     where
         T: ?Sized + ser::Serialize,
     {
-        let key = self.1.to_string();
-        self.1 += 1;
-        let mut serializer = QsSerializer::new_from_ref(&mut self.0);
-        serializer.extend_key(&key);
-        value.serialize(serializer)
+        value.serialize(self.next_element()?)
     }
 
     fn end(self) -> Result<Self::Ok> {
@@ -484,10 +1028,7 @@ impl<W: Write> ser::SerializeSeq for QsSeq<'_, W> {
     where
         T: ?Sized + ser::Serialize,
     {
-        let mut serializer = QsSerializer::new_from_ref(&mut self.0);
-        serializer.extend_key(&self.1.to_string());
-        self.1 += 1;
-        value.serialize(serializer)
+        value.serialize(self.next_element()?)
     }
     fn end(self) -> Result<Self::Ok> {
         Ok(())
@@ -502,7 +1043,7 @@ impl<W: Write> ser::SerializeStruct for QsSerializer<'_, W> {
         T: ?Sized + ser::Serialize,
     {
         let mut serializer = QsSerializer::new_from_ref(self);
-        serializer.extend_key(key);
+        serializer.extend_key(key)?;
         value.serialize(serializer)
     }
     fn end(self) -> Result<Self::Ok> {
@@ -519,7 +1060,7 @@ impl<W: Write> ser::SerializeStructVariant for QsSerializer<'_, W> {
         T: ?Sized + ser::Serialize,
     {
         let mut serializer = QsSerializer::new_from_ref(self);
-        serializer.extend_key(key);
+        serializer.extend_key(key)?;
         value.serialize(serializer)
     }
 
@@ -537,7 +1078,7 @@ impl<W: Write> ser::SerializeTupleVariant for QsSeq<'_, W> {
         T: ?Sized + ser::Serialize,
     {
         let mut serializer = QsSerializer::new_from_ref(&mut self.0);
-        serializer.extend_key(&self.1.to_string());
+        serializer.extend_key(&self.1.to_string())?;
         self.1 += 1;
         value.serialize(serializer)
     }
@@ -556,7 +1097,7 @@ impl<W: Write> ser::SerializeTupleStruct for QsSeq<'_, W> {
         T: ?Sized + ser::Serialize,
     {
         let mut serializer = QsSerializer::new_from_ref(&mut self.0);
-        serializer.extend_key(&self.1.to_string());
+        serializer.extend_key(&self.1.to_string())?;
         self.1 += 1;
         value.serialize(serializer)
     }
@@ -574,7 +1115,10 @@ impl<W: Write> ser::SerializeMap for QsMap<'_, W> {
     where
         T: ?Sized + ser::Serialize,
     {
-        self.1 = Some(Cow::from(key.serialize(StringSerializer)?));
+        let mut serializer = QsSerializer::new_from_ref(&mut self.0);
+        key.serialize(KeySerializer(&mut serializer))?;
+        let depth = serializer.depth;
+        self.1 = serializer.key.map(|key| (key, depth));
         Ok(())
     }
 
@@ -582,13 +1126,10 @@ impl<W: Write> ser::SerializeMap for QsMap<'_, W> {
     where
         T: ?Sized + ser::Serialize,
     {
+        let (key, depth) = self.1.take().ok_or_else(Error::no_key)?;
         let mut serializer = QsSerializer::new_from_ref(&mut self.0);
-        if let Some(ref key) = self.1 {
-            serializer.extend_key(key);
-        } else {
-            return Err(Error::no_key());
-        }
-        self.1 = None;
+        serializer.key = Some(key);
+        serializer.depth = depth;
         value.serialize(serializer)
     }
 
@@ -602,25 +1143,28 @@ impl<W: Write> ser::SerializeMap for QsMap<'_, W> {
         V: ?Sized + ser::Serialize,
     {
         let mut serializer = QsSerializer::new_from_ref(&mut self.0);
-        serializer.extend_key(&key.serialize(StringSerializer)?);
+        key.serialize(KeySerializer(&mut serializer))?;
         value.serialize(serializer)
     }
 }
 
-struct StringSerializer;
+/// Serializes a map key directly onto the key of a `QsSerializer`, so that
+/// string keys are not copied into an intermediate `String`.
+struct KeySerializer<'s, 'a, W: Write>(&'s mut QsSerializer<'a, W>);
 
-impl ser::Serializer for StringSerializer {
-    type Ok = String;
+impl<W: Write> ser::Serializer for KeySerializer<'_, '_, W> {
+    type Ok = ();
     type Error = Error;
-    type SerializeSeq = ser::Impossible<String, Error>;
-    type SerializeTuple = ser::Impossible<String, Error>;
-    type SerializeTupleStruct = ser::Impossible<String, Error>;
-    type SerializeTupleVariant = ser::Impossible<String, Error>;
-    type SerializeMap = ser::Impossible<String, Error>;
-    type SerializeStruct = ser::Impossible<String, Error>;
-    type SerializeStructVariant = ser::Impossible<String, Error>;
+    type SerializeSeq = ser::Impossible<(), Error>;
+    type SerializeTuple = ser::Impossible<(), Error>;
+    type SerializeTupleStruct = ser::Impossible<(), Error>;
+    type SerializeTupleVariant = ser::Impossible<(), Error>;
+    type SerializeMap = ser::Impossible<(), Error>;
+    type SerializeStruct = ser::Impossible<(), Error>;
+    type SerializeStructVariant = ser::Impossible<(), Error>;
 
     serialize_as_string! {
+        Key
         bool => serialize_bool,
         u8  => serialize_u8,
         u16 => serialize_u16,
@@ -633,11 +1177,23 @@ impl ser::Serializer for StringSerializer {
         f32 => serialize_f32,
         f64 => serialize_f64,
         char => serialize_char,
-        &str => serialize_str,
+    }
+
+    fn serialize_str(self, value: &str) -> Result<Self::Ok> {
+        self.0.extend_key(value)
     }
 
     fn serialize_bytes(self, value: &[u8]) -> Result<Self::Ok> {
-        Ok(String::from_utf8_lossy(value).to_string())
+        self.0.extend_key(&String::from_utf8_lossy(value))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok> {
+        self.0.extend_key(variant)
     }
 
     /// Returns an error.
@@ -650,15 +1206,6 @@ impl ser::Serializer for StringSerializer {
         Err(Error::Unsupported)
     }
 
-    fn serialize_unit_variant(
-        self,
-        _name: &'static str,
-        _variant_index: u32,
-        variant: &'static str,
-    ) -> Result<Self::Ok> {
-        Ok(variant.to_string())
-    }
-
     /// Returns an error.
     fn serialize_newtype_struct<T: ?Sized + ser::Serialize>(
         self,
@@ -694,6 +1241,7 @@ impl ser::Serializer for StringSerializer {
         Err(Error::Unsupported)
     }
 
+    /// Returns an error.
     fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
         Err(Error::Unsupported)
     }
@@ -707,6 +1255,7 @@ impl ser::Serializer for StringSerializer {
         Err(Error::Unsupported)
     }
 
+    /// Returns an error.
     fn serialize_tuple_variant(
         self,
         _name: &'static str,
@@ -717,14 +1266,17 @@ impl ser::Serializer for StringSerializer {
         Err(Error::Unsupported)
     }
 
+    /// Returns an error.
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
         Err(Error::Unsupported)
     }
 
+    /// Returns an error.
     fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
         Err(Error::Unsupported)
     }
 
+    /// Returns an error.
     fn serialize_struct_variant(
         self,
         _name: &'static str,
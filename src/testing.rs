@@ -0,0 +1,63 @@
+//! Helpers for testing types which are (de)serialized as querystrings.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate serde_derive;
+//! # extern crate serde_qs;
+//! use serde_qs::testing::{assert_round_trip, assert_serializes_to};
+//!
+//! #[derive(Debug, Deserialize, PartialEq, Serialize)]
+//! struct Query {
+//!     name: String,
+//!     ids: Vec<u8>,
+//! }
+//!
+//! # fn main(){
+//! let q = Query { name: "Alice".to_owned(), ids: vec![1, 2] };
+//! assert_serializes_to(&q, "name=Alice&ids[0]=1&ids[1]=2");
+//! assert_round_trip(q);
+//! # }
+//! ```
+
+use serde::de::DeserializeOwned;
+use serde::ser::Serialize;
+
+use std::fmt::Debug;
+
+/// Asserts that `value` is equal to itself after serializing and then
+/// deserializing it.
+///
+/// # Panics
+///
+/// If either step fails, or the values differ.
+pub fn assert_round_trip<T>(value: T)
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let encoded = match crate::to_string(&value) {
+        Ok(encoded) => encoded,
+        Err(e) => panic!("failed to serialize {:?}: {}", value, e),
+    };
+    match crate::from_str::<T>(&encoded) {
+        Ok(decoded) => assert_eq!(decoded, value, "round trip through {:?}", encoded),
+        Err(e) => panic!("failed to deserialize {:?}: {}", encoded, e),
+    }
+}
+
+/// Asserts that `value` serializes to exactly `expected`.
+///
+/// Use [`assert_eq_qs!`](crate::assert_eq_qs) to ignore the order of the
+/// pairs instead.
+///
+/// # Panics
+///
+/// If serializing fails, or the output differs.
+pub fn assert_serializes_to<T>(value: T, expected: &str)
+where
+    T: Serialize + Debug,
+{
+    match crate::to_string(&value) {
+        Ok(actual) => assert_eq!(actual, expected, "serializing {:?}", value),
+        Err(e) => panic!("failed to serialize {:?}: {}", value, e),
+    }
+}
@@ -0,0 +1,204 @@
+//! Functions for (de)serializing a `SystemTime` as a Unix timestamp, for use
+//! with `#[serde(with = "serde_qs::unix_timestamp")]`.
+//!
+//! Times are serialized as whole seconds since the Unix epoch, rounding down.
+//! Deserialization accepts either an integer number of seconds, or an
+//! [RFC 3339](https://www.rfc-editor.org/rfc/rfc3339) timestamp such as
+//! `2021-06-01T12:00:00Z`. Note that a `+` in a querystring decodes to a
+//! space, so positive UTC offsets must be encoded as `%2B`.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate serde_derive;
+//! # extern crate serde_qs;
+//! use std::time::{Duration, SystemTime, UNIX_EPOCH};
+//!
+//! #[derive(Debug, Deserialize, PartialEq, Serialize)]
+//! struct Query {
+//!     #[serde(with = "serde_qs::unix_timestamp")]
+//!     since: SystemTime,
+//! }
+//!
+//! # fn main(){
+//! let query = Query { since: UNIX_EPOCH + Duration::from_secs(1622548800) };
+//! assert_eq!(serde_qs::to_string(&query).unwrap(), "since=1622548800");
+//! assert_eq!(serde_qs::from_str::<Query>("since=1622548800").unwrap(), query);
+//! assert_eq!(
+//!     serde_qs::from_str::<Query>("since=2021-06-01T12:00:00Z").unwrap(),
+//!     query);
+//! # }
+//! ```
+
+use serde::{de, ser};
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Serializes `time` as the number of seconds since the Unix epoch.
+pub fn serialize<S>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: ser::Serializer,
+{
+    let secs = match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => i64::try_from(since.as_secs()).map_err(ser::Error::custom)?,
+        Err(e) => {
+            let before = e.duration();
+            let secs = i64::try_from(before.as_secs()).map_err(ser::Error::custom)?;
+            // Round down, towards the earlier second.
+            -secs - i64::from(before.subsec_nanos() > 0)
+        }
+    };
+    serializer.serialize_i64(secs)
+}
+
+/// Deserializes a `SystemTime` from a number of seconds since the Unix epoch,
+/// or from an RFC 3339 timestamp.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<SystemTime, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    deserializer.deserialize_any(TimestampVisitor)
+}
+
+struct TimestampVisitor;
+
+impl<'de> de::Visitor<'de> for TimestampVisitor {
+    type Value = SystemTime;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a Unix timestamp or an RFC 3339 timestamp")
+    }
+
+    fn visit_i64<E: de::Error>(self, secs: i64) -> Result<SystemTime, E> {
+        from_unix(secs, 0).ok_or_else(|| E::custom("timestamp out of range"))
+    }
+
+    fn visit_u64<E: de::Error>(self, secs: u64) -> Result<SystemTime, E> {
+        UNIX_EPOCH
+            .checked_add(Duration::from_secs(secs))
+            .ok_or_else(|| E::custom("timestamp out of range"))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<SystemTime, E> {
+        if let Ok(secs) = value.parse::<i64>() {
+            return self.visit_i64(secs);
+        }
+        let (secs, nanos) = parse_rfc3339(value)
+            .ok_or_else(|| E::invalid_value(de::Unexpected::Str(value), &self))?;
+        from_unix(secs, nanos).ok_or_else(|| E::custom("timestamp out of range"))
+    }
+}
+
+/// Converts seconds and nanoseconds since the Unix epoch into a `SystemTime`.
+fn from_unix(secs: i64, nanos: u32) -> Option<SystemTime> {
+    if secs >= 0 {
+        UNIX_EPOCH.checked_add(Duration::new(secs as u64, nanos))
+    } else {
+        UNIX_EPOCH
+            .checked_sub(Duration::from_secs(secs.unsigned_abs()))?
+            .checked_add(Duration::from_nanos(u64::from(nanos)))
+    }
+}
+
+/// Parses an RFC 3339 timestamp, e.g. `2021-06-01T12:00:00.5+02:00`, into
+/// seconds and nanoseconds since the Unix epoch.
+fn parse_rfc3339(input: &str) -> Option<(i64, u32)> {
+    fn number(input: &[u8]) -> Option<i64> {
+        if input.is_empty() || !input.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        Some(input.iter().fold(0, |n, &b| n * 10 + i64::from(b - b'0')))
+    }
+
+    let b = input.as_bytes();
+    if b.len() < 20
+        || b[4] != b'-'
+        || b[7] != b'-'
+        || !matches!(b[10], b'T' | b't' | b' ')
+        || b[13] != b':'
+        || b[16] != b':'
+    {
+        return None;
+    }
+    let year = number(&b[0..4])?;
+    let month = number(&b[5..7])?;
+    let day = number(&b[8..10])?;
+    let hour = number(&b[11..13])?;
+    let minute = number(&b[14..16])?;
+    let second = number(&b[17..19])?;
+    if !(1..=12).contains(&month)
+        || day < 1
+        || day > days_in_month(year, month)
+        || hour > 23
+        || minute > 59
+        // Allow for leap seconds, which are folded into the next second.
+        || second > 60
+    {
+        return None;
+    }
+
+    let mut rest = &b[19..];
+    let mut nanos = 0;
+    if let Some(frac) = rest.strip_prefix(b".") {
+        let digits = frac.iter().take_while(|b| b.is_ascii_digit()).count();
+        if digits == 0 {
+            return None;
+        }
+        // Digits beyond nanosecond precision are ignored.
+        nanos = frac[..digits]
+            .iter()
+            .chain(std::iter::repeat(&b'0'))
+            .take(9)
+            .fold(0, |n, &b| n * 10 + u32::from(b - b'0'));
+        rest = &frac[digits..];
+    }
+
+    let offset = match rest {
+        b"Z" | b"z" => 0,
+        [sign @ (b'+' | b'-'), h1, h2, b':', m1, m2] => {
+            let hours = number(&[*h1, *h2])?;
+            let minutes = number(&[*m1, *m2])?;
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let offset = hours * 3600 + minutes * 60;
+            if *sign == b'-' {
+                -offset
+            } else {
+                offset
+            }
+        }
+        _ => return None,
+    };
+
+    let days = days_from_civil(year, month, day);
+    let secs = days * 86400 + hour * 3600 + minute * 60 + second - offset;
+    Some((secs, nanos))
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Returns the number of days between the Unix epoch and the given date in
+/// the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // See http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
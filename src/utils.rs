@@ -1,6 +1,9 @@
 use percent_encoding::{AsciiSet, NON_ALPHANUMERIC};
 use std::borrow::Cow;
+use std::cmp::Ordering;
 
+/// The characters percent-encoded by default when serializing: everything
+/// except ASCII alphanumerics, ` `, `*`, `-`, `.` and `_`.
 pub const QS_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
     .remove(b' ')
     .remove(b'*')
@@ -8,7 +11,7 @@ pub const QS_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
     .remove(b'.')
     .remove(b'_');
 
-pub fn replace_space(input: &str) -> Cow<str> {
+pub fn replace_space(input: &str) -> Cow<'_, str> {
     match input.as_bytes().iter().position(|&b| b == b' ') {
         None => Cow::Borrowed(input),
         Some(first_position) => {
@@ -23,3 +26,93 @@ pub fn replace_space(input: &str) -> Cow<str> {
         }
     }
 }
+
+/// Splits a bracketed key such as `a[b][0]` into its segments
+/// `["a", "b", "0"]`.
+pub fn split_key(key: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    let (root, mut rest) = match key.find('[') {
+        Some(idx) => (&key[..idx], &key[idx..]),
+        None => (key, ""),
+    };
+    segments.push(root);
+    while let Some(stripped) = rest.strip_prefix('[') {
+        match stripped.find(']') {
+            Some(end) => {
+                segments.push(&stripped[..end]);
+                rest = &stripped[end + 1..];
+            }
+            None => {
+                segments.push(stripped);
+                rest = "";
+            }
+        }
+    }
+    if !rest.is_empty() {
+        // Trailing characters after the last closing bracket are kept as a
+        // final segment so that no part of the key is lost.
+        segments.push(rest);
+    }
+    segments
+}
+
+/// Splits a decoded key in bracket notation into its path of segments.
+///
+/// An unindexed sequence segment, as in `a[]`, is returned as an empty
+/// string. Characters following the last closing bracket are kept as a final
+/// segment, and an unclosed bracket runs to the end of the key.
+///
+/// ```
+/// use serde_qs::parse_key_path;
+///
+/// assert_eq!(parse_key_path("a[b][c]"), ["a", "b", "c"]);
+/// assert_eq!(parse_key_path("a[]"), ["a", ""]);
+/// assert_eq!(parse_key_path("a"), ["a"]);
+/// ```
+pub fn parse_key_path(key: &str) -> Vec<String> {
+    split_key(key).into_iter().map(str::to_owned).collect()
+}
+
+/// Joins a path of segments into a key in bracket notation, reversing
+/// [`parse_key_path`].
+///
+/// The first segment is the root, and each following segment is wrapped in
+/// brackets, so that an empty segment gives `[]`. No percent-encoding is
+/// applied.
+///
+/// ```
+/// use serde_qs::build_key_path;
+///
+/// assert_eq!(build_key_path(&["a", "b", "c"]), "a[b][c]");
+/// assert_eq!(build_key_path(&["a", ""]), "a[]");
+/// ```
+pub fn build_key_path(parts: &[&str]) -> String {
+    let mut key = String::new();
+    if let Some((root, rest)) = parts.split_first() {
+        key.push_str(root);
+        for part in rest {
+            key.push('[');
+            key.push_str(part);
+            key.push(']');
+        }
+    }
+    key
+}
+
+/// Compares two keys segment by segment. Segments which are both integers
+/// (i.e. sequence indices) are compared numerically, so that `a[2]` sorts
+/// before `a[10]`.
+pub fn compare_keys(a: &str, b: &str) -> Ordering {
+    let a = split_key(a);
+    let b = split_key(b);
+    for (a, b) in a.iter().zip(b.iter()) {
+        let ord = match (a.parse::<usize>(), b.parse::<usize>()) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            _ => a.cmp(b),
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    a.len().cmp(&b.len())
+}
@@ -0,0 +1,150 @@
+//! An untyped querystring value.
+
+use serde::de;
+use serde::ser;
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Any value which can be represented in a querystring, for when the schema
+/// is not known ahead of time.
+///
+/// Values are kept as strings, maps are ordered by key, and sequences are
+/// ordered by index.
+///
+/// ```
+/// use serde_qs::QsValue;
+///
+/// let value: QsValue = serde_qs::from_str("a[b]=1&c[]=x&c[]=y").unwrap();
+/// assert_eq!(value.get("a").and_then(|a| a.get("b")), Some(&QsValue::from("1")));
+/// assert_eq!(value.get("c").and_then(QsValue::as_seq).map(Vec::len), Some(2));
+/// assert_eq!(serde_qs::to_string(&value).unwrap(), "a[b]=1&c[0]=x&c[1]=y");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum QsValue {
+    /// A flat value.
+    String(String),
+    /// Values keyed by the next segment of the key, as in `a[b]=1`.
+    Map(BTreeMap<String, QsValue>),
+    /// Values of a sequence, as in `a[0]=1` or `a[]=1`.
+    Seq(Vec<QsValue>),
+}
+
+impl QsValue {
+    /// Returns the string if this is a flat value.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            QsValue::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the entries if this is a map.
+    pub fn as_map(&self) -> Option<&BTreeMap<String, QsValue>> {
+        match *self {
+            QsValue::Map(ref map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements if this is a sequence.
+    pub fn as_seq(&self) -> Option<&Vec<QsValue>> {
+        match *self {
+            QsValue::Seq(ref seq) => Some(seq),
+            _ => None,
+        }
+    }
+
+    /// Returns the value at `key` if this is a map.
+    pub fn get(&self, key: &str) -> Option<&QsValue> {
+        self.as_map().and_then(|map| map.get(key))
+    }
+}
+
+impl From<&str> for QsValue {
+    fn from(s: &str) -> Self {
+        QsValue::String(s.to_owned())
+    }
+}
+
+impl From<String> for QsValue {
+    fn from(s: String) -> Self {
+        QsValue::String(s)
+    }
+}
+
+impl ser::Serialize for QsValue {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            QsValue::String(ref s) => serializer.serialize_str(s),
+            QsValue::Map(ref map) => serializer.collect_map(map),
+            QsValue::Seq(ref seq) => serializer.collect_seq(seq),
+        }
+    }
+}
+
+impl<'de> de::Deserialize<'de> for QsValue {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(QsValueVisitor)
+    }
+}
+
+struct QsValueVisitor;
+
+macro_rules! visit_to_string {
+    ($($ty:ty => $meth:ident,)*) => {
+        $(
+            fn $meth<E: de::Error>(self, v: $ty) -> Result<QsValue, E> {
+                Ok(QsValue::String(v.to_string()))
+            }
+        )*
+    };
+}
+
+impl<'de> de::Visitor<'de> for QsValueVisitor {
+    type Value = QsValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string, map or sequence")
+    }
+
+    visit_to_string! {
+        bool => visit_bool,
+        i64 => visit_i64,
+        u64 => visit_u64,
+        f64 => visit_f64,
+        &str => visit_str,
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<QsValue, E> {
+        Ok(QsValue::String(v))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<QsValue, E> {
+        Ok(QsValue::String(String::new()))
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<QsValue, E> {
+        self.visit_unit()
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<QsValue, D::Error> {
+        de::Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<QsValue, A::Error> {
+        let mut values = Vec::new();
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(QsValue::Seq(values))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<QsValue, A::Error> {
+        let mut values = BTreeMap::new();
+        while let Some((key, value)) = map.next_entry()? {
+            values.insert(key, value);
+        }
+        Ok(QsValue::Map(values))
+    }
+}
@@ -7,8 +7,13 @@ extern crate serde_derive;
 extern crate axum_framework as axum;
 extern crate serde_qs as qs;
 
-use axum::{extract::FromRequestParts, http::StatusCode, response::IntoResponse};
-use qs::axum::{QsQuery, QsQueryConfig, QsQueryRejection};
+use axum::{
+    body::Body,
+    extract::{FromRequest, FromRequestParts},
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use qs::axum::{QsForm, QsQuery, QsQueryConfig, QsQueryRejection};
 use serde::de::Error;
 
 fn from_str<'de, D, S>(deserializer: D) -> Result<S, D::Error>
@@ -132,3 +137,84 @@ fn test_custom_qs_config() {
         assert!(s.common.remaining);
     })
 }
+
+#[test]
+fn test_form_extractor() {
+    futures::executor::block_on(async {
+        let req = axum::http::Request::builder()
+            .method("POST")
+            .uri("/test?foo=2")
+            .body(Body::from(
+                "foo=1&bars[]=0&bars[]=1&limit=100&offset=50&remaining=true",
+            ))
+            .unwrap();
+        let QsForm(s) = QsForm::<Query>::from_request(req, &()).await.unwrap();
+        assert_eq!(s.foo, 1);
+        assert_eq!(s.bars, vec![0, 1]);
+        assert_eq!(s.common.limit, 100);
+
+        // GET requests have no body, so the query is used instead.
+        let req = axum::http::Request::builder()
+            .uri("/test?foo=2&bars[]=3&limit=1&offset=0&remaining=false")
+            .body(Body::empty())
+            .unwrap();
+        let s = QsForm::<Query>::from_request(req, &()).await.unwrap();
+        assert_eq!(s.foo, 2);
+        assert_eq!(s.bars, vec![3]);
+    })
+}
+
+#[test]
+fn test_form_rejection() {
+    futures::executor::block_on(async {
+        let req = axum::http::Request::builder()
+            .method("POST")
+            .uri("/test")
+            .body(Body::from("foo=x"))
+            .unwrap();
+        let e = QsForm::<Query>::from_request(req, &()).await.unwrap_err();
+        let res = e.into_response();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("invalid digit"));
+
+        let req =
+            axum::http::Request::builder()
+                .method("POST")
+                .uri("/test")
+                .extension(QsQueryConfig::default().error_handler(|err| {
+                    QsQueryRejection::new(err, StatusCode::UNPROCESSABLE_ENTITY)
+                }))
+                .body(Body::from("foo=x"))
+                .unwrap();
+        let e = QsForm::<Query>::from_request(req, &()).await.unwrap_err();
+        assert_eq!(e.into_response().status(), StatusCode::UNPROCESSABLE_ENTITY);
+    })
+}
+
+#[test]
+fn test_form_response() {
+    futures::executor::block_on(async {
+        let form = QsForm(Query {
+            foo: 1,
+            bars: vec![2, 3],
+            common: CommonParams {
+                limit: 10,
+                offset: 0,
+                remaining: true,
+            },
+        });
+        let res = form.into_response();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[header::CONTENT_TYPE], qs::FORM_CONTENT_TYPE);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(
+            &body[..],
+            b"foo=1&bars[0]=2&bars[1]=3&limit=10&offset=0&remaining=true"
+        );
+    })
+}
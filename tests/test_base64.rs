@@ -0,0 +1,52 @@
+#![cfg(feature = "base64")]
+
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_bytes;
+extern crate serde_qs as qs;
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Query {
+    #[serde(with = "serde_bytes")]
+    data: Vec<u8>,
+}
+
+#[test]
+fn deserialize_standard_base64() {
+    // `+` and `/` must be percent-encoded, since `+` decodes to a space.
+    let rec: Query = qs::from_str("data=%2B%2F8%3D").unwrap();
+    assert_eq!(rec.data, vec![0xfb, 0xff]);
+
+    let rec: Query = qs::from_str("data=aGVsbG8%3D").unwrap();
+    assert_eq!(rec.data, b"hello");
+}
+
+#[test]
+fn deserialize_url_safe_base64() {
+    let rec: Query = qs::from_str("data=-_8%3D").unwrap();
+    assert_eq!(rec.data, vec![0xfb, 0xff]);
+}
+
+#[test]
+fn deserialize_invalid_base64() {
+    qs::from_str::<Query>("data=not%20base64!").expect_err("invalid base64");
+}
+
+#[test]
+fn deserialize_byte_buf() {
+    // Decoded bytes are always owned, so borrowing `serde_bytes::Bytes`
+    // from the input is not possible.
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        data: serde_bytes::ByteBuf,
+        rest: Option<serde_bytes::ByteBuf>,
+    }
+
+    let rec: Query = qs::from_str("data=aGVsbG8%3D").unwrap();
+    assert_eq!(rec.data.as_slice(), b"hello");
+    assert_eq!(rec.rest, None);
+
+    let rec: Query = qs::from_str("data=&rest=-_8%3D").unwrap();
+    assert!(rec.data.is_empty());
+    assert_eq!(rec.rest.unwrap().into_vec(), vec![0xfb, 0xff]);
+}
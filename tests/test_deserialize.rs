@@ -3,7 +3,7 @@ extern crate serde;
 extern crate serde_derive;
 extern crate serde_qs as qs;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 struct Address {
@@ -575,6 +575,30 @@ fn strict_mode() {
     assert_eq!(valid_utf8.field, "�");
 }
 
+#[test]
+fn strict_utf8() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        a: Vec<String>,
+    }
+
+    qs::from_str_strict_utf8::<Query>("a[0]=%E9").expect_err("invalid value");
+    qs::from_str_strict_utf8::<HashMap<String, String>>("%E9=1").expect_err("invalid key");
+    let rec: Query = qs::from_str_strict_utf8("a[0]=%C3%A9+%F0%9F%98%80").unwrap();
+    assert_eq!(rec.a, ["é 😀"]);
+
+    // Non-strict bracket handling with strict UTF-8 handling, and vice versa.
+    let config = qs::Config::new(5, false).strict_utf8(true);
+    let rec: Query = config.deserialize_str("a%5B0%5D=ok").unwrap();
+    assert_eq!(rec.a, ["ok"]);
+    config
+        .deserialize_str::<Query>("a%5B0%5D=%E9")
+        .expect_err("invalid value");
+    let config = qs::Config::default().strict_utf8(false);
+    let rec: Query = config.deserialize_str("a[0]=%E9").unwrap();
+    assert_eq!(rec.a, ["\u{FFFD}"]);
+}
+
 #[test]
 fn square_brackets_in_values() {
     map_test!("foo=%5BHello%5D", "foo"["[Hello]"]);
@@ -642,6 +666,56 @@ fn deserialize_flatten_workaround() {
     assert_eq!(rec_query.unwrap(), query);
 }
 
+#[test]
+fn deserialize_flatten_strings() {
+    // Flattened fields are buffered as strings, which string fields accept.
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Inner {
+        x: String,
+        y: String,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Outer {
+        #[serde(flatten)]
+        inner: Inner,
+    }
+
+    let rec: Outer = qs::from_str("x=1&y=2").unwrap();
+    assert_eq!(
+        rec,
+        Outer {
+            inner: Inner {
+                x: "1".to_owned(),
+                y: "2".to_owned(),
+            },
+        }
+    );
+
+    // Numbers and booleans are buffered as such when looking like them.
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Page {
+        limit: u64,
+        remaining: bool,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Query {
+        q: String,
+        #[serde(flatten)]
+        page: Page,
+    }
+
+    let rec: Query = qs::from_str_number_strings("q=a&limit=100&remaining=true").unwrap();
+    assert_eq!(
+        rec.page,
+        Page {
+            limit: 100,
+            remaining: true,
+        }
+    );
+}
+
 use serde::de::Error;
 
 fn from_str<'de, D, S>(deserializer: D) -> Result<S, D::Error>
@@ -727,6 +801,66 @@ fn deserialize_unit_types() {
     assert_eq!(test, B { t: (), a: "test" });
 }
 
+#[test]
+fn deserialize_borrowed_str() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query<'a> {
+        name: &'a str,
+        #[serde(borrow)]
+        address: Address<'a>,
+        tags: Vec<&'a str>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Address<'a> {
+        city: &'a str,
+    }
+
+    let input = "name=acme&address[city]=carrot&tags[]=a&tags[]=b";
+    let rec: Query = qs::from_str(input).unwrap();
+    assert_eq!(
+        rec,
+        Query {
+            name: "acme",
+            address: Address { city: "carrot" },
+            tags: vec!["a", "b"],
+        }
+    );
+
+    // The values point into the input.
+    let range = input.as_bytes().as_ptr_range();
+    assert!(range.contains(&rec.name.as_ptr()));
+    assert!(range.contains(&rec.address.city.as_ptr()));
+    assert!(range.contains(&rec.tags[1].as_ptr()));
+
+    // Values which must be decoded cannot be borrowed.
+    qs::from_str::<Query>("name=a%20b&address[city]=c").expect_err("encoded");
+    qs::from_str::<Query>("name=a+b&address[city]=c").expect_err("encoded");
+}
+
+#[test]
+fn deserialize_borrowed_cow() {
+    use std::borrow::Cow;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query<'a> {
+        #[serde(borrow)]
+        name: Cow<'a, str>,
+        #[serde(borrow)]
+        city: Cow<'a, str>,
+        #[serde(borrow)]
+        note: Cow<'a, str>,
+    }
+
+    let rec: Query = qs::from_str("name=acme&city=carrot+city&note=hi%21").unwrap();
+    assert_eq!(rec.name, "acme");
+    assert_eq!(rec.city, "carrot city");
+    assert_eq!(rec.note, "hi!");
+    assert!(matches!(rec.name, Cow::Borrowed(_)));
+    assert!(matches!(rec.city, Cow::Owned(_)));
+    assert!(matches!(rec.note, Cow::Owned(_)));
+}
+
 #[test]
 fn serialization_roundtrip() {
     #[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -742,3 +876,1775 @@ fn serialization_roundtrip() {
     let deserialized = serde_qs::from_str::<Data>(&serialized).unwrap();
     assert_eq!(deserialized, data);
 }
+
+#[test]
+fn deserialize_all_str() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        id: String,
+        tags: Vec<String>,
+        #[serde(deserialize_with = "from_str")]
+        count: u8,
+    }
+
+    let query: Query = qs::from_str_all_str("id=08&tags[]=1&tags[]=02&count=8").unwrap();
+    assert_eq!(
+        query,
+        Query {
+            id: "08".to_owned(),
+            tags: vec!["1".to_owned(), "02".to_owned()],
+            count: 8,
+        }
+    );
+
+    #[derive(Debug, Deserialize)]
+    struct Numeric {
+        #[allow(dead_code)]
+        id: u8,
+    }
+
+    qs::from_str_all_str::<Numeric>("id=08").expect_err("numbers are not parsed");
+    assert_eq!(
+        qs::from_str::<Query>("id=08&tags[]=1&count=8")
+            .unwrap()
+            .count,
+        8
+    );
+}
+
+#[test]
+fn deserialize_owned_bounds() {
+    // The input only needs to outlive the call for owned types.
+    fn parse<T: serde::de::DeserializeOwned>(input: String) -> Result<T, qs::Error> {
+        qs::from_str(&input)
+    }
+
+    fn parse_hrtb<T>(input: &[u8]) -> Result<T, qs::Error>
+    where
+        for<'de> T: serde::Deserialize<'de>,
+    {
+        qs::Config::default().deserialize_bytes(input)
+    }
+
+    let params: QueryParams = parse(
+        "id=42&name=Acme&phone=12345&address[city]=Carrot+City&\
+         address[postcode]=12345&user_ids[0]=1"
+            .to_owned(),
+    )
+    .unwrap();
+    assert_eq!(params.address.city, "Carrot City");
+
+    let params: Address = parse_hrtb(b"city=Carrot+City&postcode=12345").unwrap();
+    assert_eq!(params.postcode, "12345");
+}
+
+#[test]
+fn seq_element_errors() {
+    #[derive(Debug, Deserialize)]
+    struct Query {
+        #[allow(dead_code)]
+        vec: Vec<u8>,
+    }
+
+    let err = qs::from_str::<Query>("vec[0]=1&vec[3]=x").unwrap_err();
+    assert!(
+        err.to_string().contains("at sequence index 3"),
+        "unexpected error: {}",
+        err
+    );
+
+    let err = qs::from_str::<Query>("vec[]=1&vec[]=2&vec[]=256").unwrap_err();
+    assert!(
+        err.to_string().contains("at sequence index 2"),
+        "unexpected error: {}",
+        err
+    );
+}
+
+#[test]
+fn deserialize_enum_tag_key() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Shape {
+        Circle { radius: u8 },
+        Square { side: u8 },
+        Empty,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        shape: Shape,
+        name: String,
+    }
+
+    let config = qs::Config::default().enum_tag_key("type");
+
+    let shape: Shape = config.deserialize_str("type=circle&radius=2").unwrap();
+    assert_eq!(shape, Shape::Circle { radius: 2 });
+
+    let shape: Shape = config.deserialize_str("type=empty").unwrap();
+    assert_eq!(shape, Shape::Empty);
+
+    let query: Query = config
+        .deserialize_str("name=box&shape[side]=3&shape[type]=square")
+        .unwrap();
+    assert_eq!(
+        query,
+        Query {
+            shape: Shape::Square { side: 3 },
+            name: "box".to_owned(),
+        }
+    );
+
+    // Without a tag the usual representation is still accepted.
+    let shape: Shape = config.deserialize_str("circle[radius]=4").unwrap();
+    assert_eq!(shape, Shape::Circle { radius: 4 });
+
+    config
+        .deserialize_str::<Shape>("type[0]=circle&radius=2")
+        .expect_err("tag must be a single value");
+}
+
+#[test]
+fn deserialize_with_base() {
+    #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+    struct Address {
+        city: String,
+        postcode: String,
+    }
+
+    #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+    struct Query {
+        name: String,
+        address: Address,
+        tags: Vec<String>,
+        nickname: Option<String>,
+    }
+
+    let base = Query {
+        name: "Alice".to_owned(),
+        address: Address {
+            city: "Carrot City".to_owned(),
+            postcode: "12345".to_owned(),
+        },
+        tags: vec!["a".to_owned(), "b".to_owned()],
+        nickname: Some("Al".to_owned()),
+    };
+
+    let rec: Query = qs::from_str_with_base("", base.clone()).unwrap();
+    assert_eq!(rec, base);
+
+    let rec: Query =
+        qs::from_str_with_base("address[city]=Potato%20Town&tags[0]=c", base.clone()).unwrap();
+    assert_eq!(
+        rec,
+        Query {
+            address: Address {
+                city: "Potato Town".to_owned(),
+                postcode: "12345".to_owned(),
+            },
+            tags: vec!["c".to_owned()],
+            ..base.clone()
+        }
+    );
+
+    let rec: Query = qs::from_str_with_base("nickname=Ali&name=Bob", base.clone()).unwrap();
+    assert_eq!(rec.name, "Bob");
+    assert_eq!(rec.nickname, Some("Ali".to_owned()));
+
+    qs::from_str_with_base::<Query>("address=Nowhere", base).expect_err("address must be a map");
+
+    #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+    struct Sparse {
+        tags: Vec<String>,
+        scores: BTreeMap<String, u8>,
+        nickname: Option<String>,
+    }
+
+    let base = Sparse {
+        tags: vec![],
+        scores: BTreeMap::new(),
+        nickname: Some(String::new()),
+    };
+
+    let rec: Sparse = qs::from_str_with_base("", base.clone()).unwrap();
+    assert_eq!(rec, base);
+
+    let rec: Sparse = qs::from_str_with_base("scores[a]=1", base.clone()).unwrap();
+    assert_eq!(rec.scores, vec![("a".to_owned(), 1)].into_iter().collect());
+    assert_eq!(rec.nickname, Some(String::new()));
+}
+
+#[test]
+fn deserialize_lenient() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        count: u32,
+        delta: i8,
+        ratio: f32,
+        ids: Vec<u64>,
+    }
+
+    let rec: Query =
+        qs::from_str_lenient("count=3.7&delta=-2.9&ratio=0.5&ids[0]=3e2&ids[1]=7").unwrap();
+    assert_eq!(
+        rec,
+        Query {
+            count: 3,
+            delta: -2,
+            ratio: 0.5,
+            ids: vec![300, 7],
+        }
+    );
+
+    qs::from_str::<Query>("count=3.7&delta=1&ratio=0.5").expect_err("strict parsing");
+    qs::from_str_lenient::<Query>("count=-1.5&delta=1&ratio=0.5").expect_err("negative u32");
+    qs::from_str_lenient::<Query>("count=1&delta=200.1&ratio=0.5").expect_err("out of range i8");
+    qs::from_str_lenient::<Query>("count=1e40&delta=1&ratio=0.5").expect_err("out of range u32");
+    qs::from_str_lenient::<Query>("count=NaN&delta=1&ratio=0.5").expect_err("not a number");
+    qs::from_str_lenient::<Query>("count=abc&delta=1&ratio=0.5").expect_err("not a number");
+}
+
+#[test]
+fn deserialize_bom() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        a: u8,
+        b: String,
+    }
+
+    let input = "\u{feff}a=1&b=x";
+    let expected = Query {
+        a: 1,
+        b: "x".to_owned(),
+    };
+
+    assert_eq!(
+        qs::from_bytes_with_bom_stripping::<Query>(input.as_bytes()).unwrap(),
+        expected
+    );
+    assert_eq!(
+        qs::Config::default()
+            .strip_bom(true)
+            .deserialize_str::<Query>(input)
+            .unwrap(),
+        expected
+    );
+    // Input without a BOM is unaffected.
+    assert_eq!(
+        qs::from_bytes_with_bom_stripping::<Query>(b"a=1&b=x").unwrap(),
+        expected
+    );
+
+    // By default the BOM is part of the first key.
+    qs::from_str::<Query>(input).expect_err("missing field `a`");
+}
+
+#[test]
+fn deserialize_single_pair() {
+    let pair: (String, String) = qs::from_str("token=abc123").unwrap();
+    assert_eq!(pair, ("token".to_owned(), "abc123".to_owned()));
+
+    let pair: (String, u32) = qs::from_str("page=3").unwrap();
+    assert_eq!(pair, ("page".to_owned(), 3));
+
+    let pair: (String, Vec<u8>) = qs::from_str("ids[0]=1&ids[1]=2").unwrap();
+    assert_eq!(pair, ("ids".to_owned(), vec![1, 2]));
+
+    qs::from_str::<(String, String)>("").expect_err("no pairs");
+    qs::from_str::<(String, String)>("a=1&b=2").expect_err("two pairs");
+    qs::from_str::<(String, String, String)>("a=1").expect_err("3-tuple");
+}
+
+#[test]
+fn deserialize_collect_errors() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Address {
+        city: String,
+        postcode: u32,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        name: String,
+        age: u8,
+        tags: Vec<u8>,
+        address: Address,
+        nickname: Option<String>,
+    }
+
+    let rec: Query = qs::from_str_collect_errors(
+        "name=Alice&age=24&tags[0]=1&address[city]=X&address[postcode]=1",
+    )
+    .unwrap();
+    assert_eq!(rec.age, 24);
+
+    let errors = qs::from_str_collect_errors::<Query>(
+        "name=Alice&age=old&tags[0]=x&address[city]=X&address[postcode]=y",
+    )
+    .unwrap_err();
+    assert_eq!(errors.len(), 3);
+    for error in &errors {
+        assert!(error.to_string().contains("invalid digit"), "{}", error);
+    }
+
+    // A missing field ends the collection, since it belongs to no key.
+    let errors = qs::from_str_collect_errors::<Query>("age=old&tags[0]=1").unwrap_err();
+    assert_eq!(errors.len(), 2);
+    assert!(errors[1].to_string().contains("missing field"));
+
+    // Syntax errors are reported on their own.
+    let errors = qs::from_str_collect_errors::<Query>("a[b=1").unwrap_err();
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn deserialize_number_strings() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(untagged)]
+    enum Value {
+        Bool(bool),
+        Int(i64),
+        Float(f64),
+        Text(String),
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        a: Value,
+        b: Value,
+        c: Value,
+        d: Value,
+        e: Value,
+        f: Value,
+        zip: String,
+        count: u8,
+    }
+
+    let rec: Query =
+        qs::from_str_number_strings("a=-12&b=1.5e3&c=true&d=hello&e=NaN&f=&zip=08540&count=3")
+            .unwrap();
+    assert_eq!(
+        rec,
+        Query {
+            a: Value::Int(-12),
+            b: Value::Float(1500.0),
+            c: Value::Bool(true),
+            d: Value::Text("hello".to_owned()),
+            e: Value::Text("NaN".to_owned()),
+            f: Value::Text("".to_owned()),
+            zip: "08540".to_owned(),
+            count: 3,
+        }
+    );
+
+    // Without inference, untagged enums only ever see strings.
+    let rec: Query = qs::from_str("a=-12&b=1&c=true&d=x&e=y&f=&zip=1&count=3").unwrap();
+    assert_eq!(rec.a, Value::Text("-12".to_owned()));
+}
+
+#[test]
+fn max_depth_across_pairs() {
+    // The depth limit applies to each pair on its own, however many pairs
+    // precede it.
+    let config = qs::Config::new(0, true);
+    let map: HashMap<String, String> = config.deserialize_str("a=1&b[c]=2&d[e][f]=3").unwrap();
+    assert_eq!(map["a"], "1");
+    assert_eq!(map["b[c]"], "2");
+    assert_eq!(map["d[e][f]"], "3");
+
+    let config = qs::Config::new(1, true);
+    let map: HashMap<String, HashMap<String, String>> = config
+        .deserialize_str("a[x]=1&b[c]=2&d[e][f]=3&g[h][i][j]=4")
+        .unwrap();
+    assert_eq!(map["a"]["x]"], "1");
+    assert_eq!(map["b"]["c]"], "2");
+    assert_eq!(map["d"]["e][f]"], "3");
+    assert_eq!(map["g"]["h][i][j]"], "4");
+}
+
+#[test]
+fn bracket_depth_limit() {
+    let config = qs::Config::default().bracket_depth_limit(2);
+    let map: HashMap<String, HashMap<String, HashMap<String, String>>> =
+        config.deserialize_str("a[b][c]=1&d[e][f]=2").unwrap();
+    assert_eq!(map["a"]["b"]["c"], "1");
+
+    config
+        .deserialize_str::<HashMap<String, String>>("a=1&b[c][d][e]=2")
+        .expect_err("too many brackets");
+    // Brackets in values do not count.
+    let map: HashMap<String, String> = config.deserialize_str("a=[[[[]]]]").unwrap();
+    assert_eq!(map["a"], "[[[[]]]]");
+
+    // Encoded brackets count in non-strict mode, where they are parsed.
+    let config = qs::Config::new(5, false).bracket_depth_limit(1);
+    config
+        .deserialize_str::<HashMap<String, HashMap<String, String>>>("a%5Bb%5D%5Bc%5D=1")
+        .expect_err("too many brackets");
+    let map: HashMap<String, HashMap<String, String>> =
+        config.deserialize_str("a%5Bb%5D=1").unwrap();
+    assert_eq!(map["a"]["b"], "1");
+}
+
+#[test]
+fn deserialize_top_level_seq() {
+    let rec: Vec<HashMap<String, String>> =
+        qs::from_str("[0][name]=Alice&[0][age]=24&[1][name]=Bob&[1][age]=30").unwrap();
+    assert_eq!(rec.len(), 2);
+    assert_eq!(rec[0]["name"], "Alice");
+    assert_eq!(rec[1]["age"], "30");
+
+    // Indices are ordered numerically.
+    let input = (0..12)
+        .map(|i| format!("[{}]={}", i, i))
+        .collect::<Vec<_>>()
+        .join("&");
+    let rec: Vec<u8> = qs::from_str(&input).unwrap();
+    assert_eq!(rec, (0..12).collect::<Vec<_>>());
+
+    let rec: Vec<String> = qs::from_str("[]=a&[]=b").unwrap();
+    assert_eq!(rec, vec!["a", "b"]);
+
+    let rec: Vec<u8> = qs::from_str("").unwrap();
+    assert!(rec.is_empty());
+
+    qs::from_str::<Vec<String>>("a=1&b=2").expect_err("keys are not indices");
+    qs::from_str::<Vec<String>>("[0]=1&a=2").expect_err("mixed root");
+}
+
+#[test]
+fn key_limit() {
+    let input = (0..1001)
+        .map(|i| format!("k{}=v", i))
+        .collect::<Vec<_>>()
+        .join("&");
+    qs::from_str::<HashMap<String, String>>(&input).expect_err("default limit of 1000 keys");
+    let map: HashMap<String, String> = qs::Config::default()
+        .key_limit(2000)
+        .max_value_count(2000)
+        .deserialize_str(&input)
+        .unwrap();
+    assert_eq!(map.len(), 1001);
+
+    let config = qs::Config::default().key_limit(2);
+    // Nested keys only count once at the top level.
+    let map: HashMap<String, HashMap<String, String>> = config
+        .deserialize_str("a[x]=1&a[y]=2&a[z]=3&b[x]=4")
+        .unwrap();
+    assert_eq!(map["a"].len(), 3);
+    config
+        .deserialize_str::<HashMap<String, String>>("a=1&b=2&c=3")
+        .expect_err("three keys");
+    config
+        .deserialize_str::<Vec<String>>("[]=1&[]=2&[]=3")
+        .expect_err("three elements");
+}
+
+#[test]
+fn max_value_count() {
+    let input = (0..1001)
+        .map(|i| format!("a[]={}", i))
+        .collect::<Vec<_>>()
+        .join("&");
+    let err = qs::from_str::<HashMap<String, Vec<u32>>>(&input).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("exceeded the limit of 1000 values"));
+    let map: HashMap<String, Vec<u32>> = qs::Config::default()
+        .max_value_count(1001)
+        .deserialize_str(&input)
+        .unwrap();
+    assert_eq!(map["a"].len(), 1001);
+
+    // Values are counted across all keys and kinds of sequences.
+    let config = qs::Config::default().max_value_count(4);
+    let map: HashMap<String, HashMap<String, String>> =
+        config.deserialize_str("a[x]=1&a[y]=2&b[x]=3&b[y]").unwrap();
+    assert_eq!(map["b"]["y"], "");
+    config
+        .deserialize_str::<HashMap<String, Vec<u8>>>("a[0]=1&a[1]=2&b[]=3&b[]=4&c=5")
+        .expect_err("five values");
+    config
+        .deserialize_str::<HashMap<String, Vec<u8>>>("a=1&a=2&a=3&a=4&a=5")
+        .expect_err("five repeated values");
+    // Empty pairs are not values.
+    config
+        .deserialize_str::<HashMap<String, String>>("a=1&&&&b=2&")
+        .unwrap();
+    config
+        .deserialize_pairs::<HashMap<String, String>, _, _, _>(vec![("a", "1"); 5])
+        .expect_err("five pairs");
+}
+
+#[test]
+fn value_length_limit() {
+    let long = "x".repeat(64 * 1024 + 1);
+    let err = qs::from_str::<HashMap<String, String>>(&format!("a=1&b={}", long))
+        .expect_err("default limit of 64 KiB");
+    assert!(err
+        .to_string()
+        .contains("value length 65537 exceeds limit 65536"));
+    let map: HashMap<String, String> = qs::Config::default()
+        .value_length_limit(usize::MAX)
+        .deserialize_str(&format!("a={}", long))
+        .unwrap();
+    assert_eq!(map["a"], long);
+
+    // The limit applies to the encoded value.
+    let config = qs::Config::default().value_length_limit(3);
+    let map: HashMap<String, String> = config.deserialize_str("a=abc&b=def&d").unwrap();
+    assert_eq!(map["a"], "abc");
+    config
+        .deserialize_str::<HashMap<String, String>>("a=%20b")
+        .expect_err("four encoded bytes");
+}
+
+#[test]
+fn key_length_limit() {
+    let long = "k".repeat(257);
+    let err = qs::from_str::<HashMap<String, String>>(&format!("a=1&{}=2", long))
+        .expect_err("default limit of 256 bytes");
+    assert!(err.to_string().contains("key length 257 exceeds limit 256"));
+    let map: HashMap<String, String> = qs::Config::default()
+        .key_length_limit(1000)
+        .deserialize_str(&format!("{}=2", long))
+        .unwrap();
+    assert_eq!(map[&long], "2");
+
+    // Brackets count towards the length of the key.
+    let config = qs::Config::default().key_length_limit(4);
+    let map: HashMap<String, HashMap<String, String>> =
+        config.deserialize_str("a[b]=1&a[c]").unwrap();
+    assert_eq!(map["a"]["b"], "1");
+    config
+        .deserialize_str::<HashMap<String, HashMap<String, String>>>("a[bc]=1")
+        .expect_err("five bytes");
+}
+
+#[test]
+fn deserialize_vec_deque() {
+    use std::collections::VecDeque;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        indexed: VecDeque<u8>,
+        unindexed: VecDeque<String>,
+    }
+
+    let rec: Query = qs::from_str("indexed[1]=2&indexed[0]=1&unindexed[]=a&unindexed[]=b").unwrap();
+    assert_eq!(rec.indexed, VecDeque::from(vec![1, 2]));
+    assert_eq!(
+        rec.unindexed,
+        VecDeque::from(vec!["a".to_owned(), "b".to_owned()])
+    );
+
+    let rec: VecDeque<u8> = qs::from_str("[0]=3&[1]=4").unwrap();
+    assert_eq!(rec, VecDeque::from(vec![3, 4]));
+}
+
+#[test]
+fn deserialize_small_collections() {
+    use arrayvec::ArrayVec;
+    use smallvec::SmallVec;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        small: SmallVec<[u8; 2]>,
+        array: ArrayVec<String, 2>,
+    }
+
+    let rec: Query = qs::from_str("small[0]=1&small[1]=2&small[2]=3&array[]=a&array[]=b").unwrap();
+    assert_eq!(rec.small.as_slice(), &[1, 2, 3]);
+    assert!(rec.small.spilled());
+    assert_eq!(rec.array.as_slice(), &["a".to_owned(), "b".to_owned()]);
+
+    qs::from_str::<Query>("small[0]=1&array[]=a&array[]=b&array[]=c")
+        .expect_err("ArrayVec is full");
+}
+
+#[test]
+fn deserialize_ignored_any() {
+    use serde::de::IgnoredAny;
+
+    for input in &[
+        "",
+        "a=1",
+        "a=1&b[c]=2&b[d][0]=3&e[]=4&e[]=5&f",
+        "[0][a]=1&[1][a]=2",
+        "a=%F0%9F%98%80",
+    ] {
+        qs::from_str::<IgnoredAny>(input).unwrap();
+    }
+
+    #[derive(Deserialize)]
+    struct Query {
+        a: u8,
+        #[allow(dead_code)]
+        b: IgnoredAny,
+    }
+    let rec: Query = qs::from_str("a=1&b[c][d]=x&b[e][]=y").unwrap();
+    assert_eq!(rec.a, 1);
+
+    qs::Config::default()
+        .repeated_keys(qs::RepeatedKeys::Error)
+        .deserialize_str::<IgnoredAny>("a=1&a=2")
+        .expect_err("multiple values for one key");
+    qs::from_str::<IgnoredAny>("a[b]c=1").expect_err("invalid syntax");
+}
+
+#[test]
+fn deserialize_recursive_box() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Node {
+        value: i32,
+        next: Option<Box<Node>>,
+    }
+
+    let rec: Node = qs::from_str("value=1&next[value]=2&next[next][value]=3").unwrap();
+    assert_eq!(
+        rec,
+        Node {
+            value: 1,
+            next: Some(Box::new(Node {
+                value: 2,
+                next: Some(Box::new(Node {
+                    value: 3,
+                    next: None,
+                })),
+            })),
+        }
+    );
+
+    // Nesting is bounded by `max_depth`.
+    let deep = "value=1&next[value]=2&next[next][value]=3&next[next][next][value]=4";
+    qs::Config::new(2, true)
+        .deserialize_str::<Node>(deep)
+        .expect_err("exceeds max_depth");
+    assert!(qs::from_str::<Node>(deep).is_ok());
+}
+
+#[test]
+fn deserialize_seeded() {
+    use serde::de::{DeserializeSeed, Deserializer};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        limit: u32,
+    }
+
+    /// Clamps `limit` to the maximum given as context.
+    struct MaxLimit(u32);
+
+    impl<'de> DeserializeSeed<'de> for MaxLimit {
+        type Value = Query;
+
+        fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Query, D::Error> {
+            let query = <Query as serde::Deserialize>::deserialize(deserializer)?;
+            Ok(Query {
+                limit: query.limit.min(self.0),
+            })
+        }
+    }
+
+    assert_eq!(
+        qs::from_str_seeded("limit=500", MaxLimit(100)).unwrap(),
+        Query { limit: 100 }
+    );
+    assert_eq!(
+        qs::from_str_seeded("limit=5", MaxLimit(100)).unwrap(),
+        Query { limit: 5 }
+    );
+    assert!(qs::from_str_seeded("limit=x", MaxLimit(100)).is_err());
+
+    let seeded: HashMap<String, u8> = qs::from_str_seeded("a=1", std::marker::PhantomData).unwrap();
+    assert_eq!(seeded["a"], 1);
+}
+
+#[test]
+fn deserialize_error_policy() {
+    use qs::{Config, ErrorPolicy};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Inner {
+        x: u8,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        #[serde(default)]
+        a: u8,
+        #[serde(default = "default_b")]
+        b: String,
+        c: Option<Vec<u8>>,
+    }
+
+    fn default_b() -> String {
+        "b".to_owned()
+    }
+
+    let input = "a=x&b=ok&c[0]=1&c[1]=y";
+    qs::from_str::<Query>(input).expect_err("fails by default");
+    Config::default()
+        .on_error(ErrorPolicy::Fail)
+        .deserialize_str::<Query>(input)
+        .expect_err("fails");
+
+    let config = Config::default().on_error(ErrorPolicy::Skip);
+    assert_eq!(
+        config.deserialize_str::<Query>(input).unwrap(),
+        Query {
+            a: 0,
+            b: "ok".to_owned(),
+            c: None,
+        }
+    );
+
+    let config = Config::default().on_error(ErrorPolicy::Default);
+    assert_eq!(
+        config.deserialize_str::<Query>(input).unwrap(),
+        Query {
+            a: 0,
+            b: "ok".to_owned(),
+            c: None,
+        }
+    );
+
+    // Skipping uses the field's own default, unlike `ErrorPolicy::Default`.
+    let input = "b[x]=1";
+    assert_eq!(
+        Config::default()
+            .on_error(ErrorPolicy::Skip)
+            .deserialize_str::<Query>(input)
+            .unwrap()
+            .b,
+        "b"
+    );
+    assert_eq!(
+        Config::default()
+            .on_error(ErrorPolicy::Default)
+            .deserialize_str::<Query>(input)
+            .unwrap()
+            .b,
+        ""
+    );
+
+    // Required fields cannot be skipped, and syntax errors are not recovered.
+    #[derive(Debug, Deserialize)]
+    struct Required {
+        #[allow(dead_code)]
+        inner: Inner,
+    }
+    let config = Config::default().on_error(ErrorPolicy::Skip);
+    config
+        .deserialize_str::<Required>("inner[x]=y")
+        .expect_err("missing field");
+    let rec: Required = Config::default()
+        .on_error(ErrorPolicy::Default)
+        .deserialize_str("inner[x]=y")
+        .unwrap();
+    assert_eq!(rec.inner, Inner { x: 0 });
+    Config::default()
+        .on_error(ErrorPolicy::Default)
+        .deserialize_str::<Query>("a[b]c=1")
+        .expect_err("invalid syntax");
+
+    // Top-level values other than maps are deserialized as without a policy.
+    for &policy in &[ErrorPolicy::Fail, ErrorPolicy::Skip, ErrorPolicy::Default] {
+        let config = Config::default().on_error(policy);
+        assert_eq!(
+            config.deserialize_str::<Vec<u32>>("0=1&1=2").unwrap(),
+            [1, 2]
+        );
+        assert_eq!(
+            config.deserialize_str::<(String, u8)>("a=1").unwrap(),
+            ("a".to_owned(), 1)
+        );
+        config.deserialize_str::<()>("").unwrap();
+    }
+}
+
+#[test]
+fn deserialize_unit_enum_values() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Status {
+        Active,
+        Inactive,
+        Pending,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        status: Status,
+        history: Vec<Status>,
+        next: Option<Status>,
+    }
+
+    let rec: Query = qs::from_str("status=active&history[0]=pending&history[1]=inactive").unwrap();
+    assert_eq!(
+        rec,
+        Query {
+            status: Status::Active,
+            history: vec![Status::Pending, Status::Inactive],
+            next: None,
+        }
+    );
+
+    let err = qs::from_str::<Query>("status=Active&history[]=active").unwrap_err();
+    assert!(
+        err.to_string().contains("unknown variant `Active`"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn config_builder() {
+    use qs::Config;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        a: HashMap<String, String>,
+        b: Vec<u8>,
+    }
+
+    let config = Config::default()
+        .pair_separator(';')
+        .key_value_separator(':');
+    let rec: Query = qs::from_str_with_config("a[x]:1=2&z;b[0]:3;;b[1]:4;a[y]", &config)
+        .expect("parses with custom separators");
+    assert_eq!(rec.a["x"], "1=2&z");
+    assert_eq!(rec.a["y"], "");
+    assert_eq!(rec.b, vec![3, 4]);
+
+    let config = config.max_depth(0);
+    let map: HashMap<String, String> = config.deserialize_str("a[x]:1;b[y]").unwrap();
+    assert_eq!(map["a[x]"], "1");
+    assert_eq!(map["b[y]"], "");
+
+    let rec: Query = qs::from_bytes_with_config(b"a[x]=1&b[]=2", &Config::default()).unwrap();
+    assert_eq!(rec.a["x"], "1");
+    let rec: Query = qs::from_reader("a[x]=1&b[]=2".as_bytes()).unwrap();
+    assert_eq!(rec.b, vec![2]);
+    let config = Config::default().strict(false);
+    let rec: Query = qs::from_reader_with_config(&b"a%5Bx%5D=1&b%5B%5D=2"[..], &config).unwrap();
+    assert_eq!(rec.a["x"], "1");
+
+    // Reading errors are returned.
+    struct Failing;
+    impl std::io::Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(std::io::ErrorKind::Other, "broken"))
+        }
+    }
+    assert!(matches!(
+        qs::from_reader::<Query, _>(Failing),
+        Err(qs::Error::Io(_))
+    ));
+}
+
+#[test]
+#[should_panic(expected = "cannot be used as a separator")]
+fn config_bracket_separator() {
+    let _ = qs::Config::default().pair_separator('[');
+}
+
+#[test]
+fn config_equal_separators() {
+    let config = qs::Config::default().pair_separator('=');
+    config
+        .deserialize_str::<HashMap<String, String>>("a=1=b=2")
+        .expect_err("separators must differ");
+
+    let config = config.pair_separator(';').key_value_separator(';');
+    config
+        .deserialize_str::<HashMap<String, String>>("a;1;b;2")
+        .expect_err("separators must differ");
+}
+
+#[test]
+fn deserialize_variant_lookup() {
+    #[derive(Debug, Deserialize, PartialEq, Eq, Hash)]
+    enum Status {
+        Active,
+        Inactive,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Shape {
+        Circle { radius: u8 },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        status: Status,
+        shape: Shape,
+        counts: HashMap<Status, u8>,
+    }
+
+    fn lookup(name: &str) -> Option<&'static str> {
+        ["Active", "Inactive", "Circle"]
+            .iter()
+            .copied()
+            .find(|variant| variant.eq_ignore_ascii_case(name))
+    }
+
+    let input = "status=active&shape[circle][radius]=2&counts[INACTIVE]=3";
+    qs::from_str::<Query>(input).expect_err("variant names are case-sensitive");
+
+    let config = qs::Config::default().variant_lookup(lookup);
+    let rec: Query = config.deserialize_str(input).unwrap();
+    assert_eq!(rec.status, Status::Active);
+    assert_eq!(rec.shape, Shape::Circle { radius: 2 });
+    assert_eq!(rec.counts[&Status::Inactive], 3);
+
+    // Unmatched names are passed through unchanged.
+    config
+        .deserialize_str::<Query>("status=unknown&shape[Circle][radius]=2")
+        .expect_err("unknown variant");
+
+    let config = config.enum_tag_key("type");
+    assert_eq!(
+        config
+            .deserialize_str::<Shape>("type=CIRCLE&radius=1")
+            .unwrap(),
+        Shape::Circle { radius: 1 }
+    );
+}
+
+#[test]
+fn deserialize_skip_encoding() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        name: String,
+        tags: Vec<String>,
+    }
+
+    let rec: Query =
+        qs::from_bytes_skip_encoding("name=Zoë+50%&tags[0]=%20&tags[1]=a b".as_bytes()).unwrap();
+    assert_eq!(
+        rec,
+        Query {
+            name: "Zoë+50%".to_owned(),
+            tags: vec!["%20".to_owned(), "a b".to_owned()],
+        }
+    );
+
+    qs::from_bytes_skip_encoding::<Query>(b"name=\xFF&tags[0]=a").expect_err("invalid UTF-8");
+
+    let config = qs::Config::default().percent_decode(false);
+    let map: HashMap<String, String> = config.deserialize_str("a%5Bb%5D=%41").unwrap();
+    assert_eq!(map["a%5Bb%5D"], "%41");
+}
+
+#[test]
+fn array_limit() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        ids: Vec<u32>,
+    }
+
+    // No limit by default, so long sequences are accepted.
+    let input = (0..300)
+        .map(|i| format!("ids[{}]={}", i, i))
+        .collect::<Vec<_>>()
+        .join("&");
+    let rec: Query = qs::from_str(&input).unwrap();
+    assert_eq!(rec.ids.len(), 300);
+
+    let config = qs::Config::default().array_limit(255);
+    let err = config.deserialize_str::<Query>(&input).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("array index 256 exceeds configured limit of 255"),
+        "{}",
+        err
+    );
+    let rec: Query = config.deserialize_str("ids[255]=1").unwrap();
+    assert_eq!(rec.ids, vec![1]);
+
+    let config = qs::Config::default().array_limit(1);
+    let rec: Query = config.deserialize_str("ids[]=1&ids[]=2").unwrap();
+    assert_eq!(rec.ids, vec![1, 2]);
+    config
+        .deserialize_str::<Query>("ids[]=1&ids[]=2&ids[]=3")
+        .expect_err("third unindexed element");
+}
+
+#[test]
+fn leading_question_mark() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        a: u8,
+        b: Option<String>,
+    }
+
+    let expected = Query { a: 1, b: None };
+    assert_eq!(qs::from_str::<Query>("?a=1").unwrap(), expected);
+    assert_eq!(qs::from_bytes::<Query>(b"?a=1").unwrap(), expected);
+    assert_eq!(
+        qs::from_bytes_with_bom_stripping::<Query>(b"\xEF\xBB\xBF?a=1").unwrap(),
+        expected
+    );
+    assert!(qs::from_str::<HashMap<String, String>>("?")
+        .unwrap()
+        .is_empty());
+
+    // Only a single leading `?` is removed.
+    let map: HashMap<String, String> = qs::from_str("??a=1&b=?").unwrap();
+    assert_eq!(map["?a"], "1");
+    assert_eq!(map["b"], "?");
+}
+
+#[test]
+fn deserialize_toml_value() {
+    use toml::Value;
+
+    let value: Value = qs::from_str("a[b]=1&c[0]=x&c[1]=y&d=z").unwrap();
+    let table = value.as_table().unwrap();
+    assert_eq!(table["a"]["b"], Value::String("1".to_owned()));
+    assert_eq!(
+        table["c"],
+        Value::Array(vec![
+            Value::String("x".to_owned()),
+            Value::String("y".to_owned())
+        ])
+    );
+    assert_eq!(table["d"], Value::String("z".to_owned()));
+
+    let map: toml::map::Map<String, Value> = qs::from_str("a[b]=1").unwrap();
+    assert!(map["a"].is_table());
+
+    let empty: Value = qs::from_str("").unwrap();
+    assert!(empty.as_table().unwrap().is_empty());
+}
+
+#[test]
+fn plus_as_space() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        #[serde(rename = "first name")]
+        first_name: String,
+        email: String,
+    }
+
+    let query: Query = qs::from_str("first+name=John+Doe&email=a%2Bb%40c.com").unwrap();
+    assert_eq!(query.first_name, "John Doe");
+    assert_eq!(query.email, "a+b@c.com");
+
+    let config = qs::Config::default().plus_as_space(false);
+    let map: HashMap<String, String> = config.deserialize_str("first+name=John+Doe%20Jr").unwrap();
+    assert_eq!(map["first+name"], "John+Doe Jr");
+}
+
+#[test]
+fn empty_string_as_none() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        name: Option<String>,
+        age: Option<u8>,
+        nickname: Option<String>,
+    }
+
+    let query: Query = qs::from_str("name=&age=").unwrap();
+    assert_eq!(
+        query,
+        Query {
+            name: None,
+            age: None,
+            nickname: None,
+        }
+    );
+
+    let config = qs::Config::default().empty_string_as_none(false);
+    let query: Query = config.deserialize_str("name=&age=1").unwrap();
+    assert_eq!(
+        query,
+        Query {
+            name: Some(String::new()),
+            age: Some(1),
+            nickname: None,
+        }
+    );
+    config
+        .deserialize_str::<Query>("age=")
+        .expect_err("empty integer");
+}
+
+#[test]
+fn indexed_sequence_order_and_gaps() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        items: Vec<String>,
+    }
+
+    let rec: Query = qs::from_str("items[2]=c&items[0]=a&items[1]=b").unwrap();
+    assert_eq!(rec.items, ["a", "b", "c"]);
+
+    // Missing indices are skipped rather than filled in, so that element
+    // types without an empty value can still be deserialized.
+    let rec: Query = qs::from_str("items[10]=b&items[3]=a").unwrap();
+    assert_eq!(rec.items, ["a", "b"]);
+
+    let config = qs::Config::default().array_limit(5);
+    config
+        .deserialize_str::<Query>("items[0]=a&items[10]=b")
+        .expect_err("index above the limit");
+}
+
+#[test]
+fn optional_fields() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Filter {
+        min: Option<u32>,
+        max: Option<u32>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        q: Option<String>,
+        filter: Option<Filter>,
+        tags: Option<Vec<String>>,
+    }
+
+    let rec: Query = qs::from_str("").unwrap();
+    assert_eq!(
+        rec,
+        Query {
+            q: None,
+            filter: None,
+            tags: None,
+        }
+    );
+
+    let rec: Query = qs::from_str("q=shoes&filter[max]=50&tags[]=red").unwrap();
+    assert_eq!(
+        rec,
+        Query {
+            q: Some("shoes".to_owned()),
+            filter: Some(Filter {
+                min: None,
+                max: Some(50),
+            }),
+            tags: Some(vec!["red".to_owned()]),
+        }
+    );
+
+    let rec: Query = qs::from_str("q=&filter[min]=").unwrap();
+    assert_eq!(rec.q, None);
+    assert_eq!(
+        rec.filter,
+        Some(Filter {
+            min: None,
+            max: None
+        })
+    );
+
+    let config = qs::Config::default().empty_string_as_none(false);
+    let rec: Query = config.deserialize_str("q=").unwrap();
+    assert_eq!(rec.q, Some(String::new()));
+}
+
+#[test]
+fn deserialize_bool_strings() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        a: bool,
+        b: bool,
+        c: Option<bool>,
+    }
+
+    for &(input, a, b) in &[
+        ("a=true&b=false", true, false),
+        ("a=1&b=0", true, false),
+        ("a=yes&b=no", true, false),
+    ] {
+        let rec: Query = qs::from_str(input).unwrap();
+        assert_eq!(rec, Query { a, b, c: None });
+    }
+    let err = qs::from_str::<Query>("a=TRUE&b=0").unwrap_err();
+    assert!(err.to_string().contains("invalid boolean"), "{}", err);
+
+    let config = qs::Config::default().bool_strings(&["on"], &["off", ""]);
+    let rec: Query = config.deserialize_str("a=on&b=&c=off").unwrap();
+    assert_eq!(
+        rec,
+        Query {
+            a: true,
+            b: false,
+            c: Some(false),
+        }
+    );
+    config
+        .deserialize_str::<Query>("a=1&b=0")
+        .expect_err("restricted to on and off");
+}
+
+#[test]
+fn deserialize_newtype_enum_values() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Filter {
+        Any,
+        Limit(u32),
+        Name(String),
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        filters: Vec<Filter>,
+    }
+
+    let rec: Query =
+        qs::from_str("filters[0][Limit]=3&filters[1]=Any&filters[2][Name]=a+b").unwrap();
+    assert_eq!(
+        rec.filters,
+        vec![
+            Filter::Limit(3),
+            Filter::Any,
+            Filter::Name("a b".to_owned())
+        ]
+    );
+
+    // A newtype variant needs a value for its contents.
+    let err = qs::from_str::<Query>("filters[0]=Limit").unwrap_err();
+    assert!(err.to_string().contains("UnitVariant"), "{}", err);
+}
+
+#[test]
+fn parse_key_path() {
+    assert_eq!(qs::parse_key_path("a[b][0]"), ["a", "b", "0"]);
+    assert_eq!(qs::parse_key_path("a[][c]"), ["a", "", "c"]);
+    assert_eq!(qs::parse_key_path("[0][a]"), ["", "0", "a"]);
+    assert_eq!(qs::parse_key_path("a[b]c"), ["a", "b", "c"]);
+    assert_eq!(qs::parse_key_path("a[b"), ["a", "b"]);
+    assert_eq!(qs::parse_key_path(""), [""]);
+}
+
+#[test]
+fn build_key_path() {
+    assert_eq!(qs::build_key_path(&["a", "b", "0"]), "a[b][0]");
+    assert_eq!(qs::build_key_path(&["", "0", "a"]), "[0][a]");
+    assert_eq!(qs::build_key_path(&["a"]), "a");
+    assert_eq!(qs::build_key_path(&[]), "");
+
+    for &key in &["a[b][c]", "a[]", "a[][c]", "[0][a]", "a"] {
+        let path = qs::parse_key_path(key);
+        let parts = path.iter().map(String::as_str).collect::<Vec<_>>();
+        assert_eq!(qs::build_key_path(&parts), key);
+    }
+}
+
+#[test]
+fn deserialize_rename_all() {
+    macro_rules! rename_all_test {
+        ($rule:literal, $input:literal) => {{
+            #[derive(Debug, Deserialize, Serialize, PartialEq)]
+            #[serde(rename_all = $rule)]
+            struct Address {
+                street_name: String,
+                post_code: u32,
+            }
+
+            #[derive(Debug, Deserialize, Serialize, PartialEq)]
+            #[serde(rename_all = $rule)]
+            struct Query {
+                first_name: String,
+                home_address: Address,
+                tag_ids: Vec<u8>,
+            }
+
+            let expected = Query {
+                first_name: "Alice".to_owned(),
+                home_address: Address {
+                    street_name: "Main".to_owned(),
+                    post_code: 12345,
+                },
+                tag_ids: vec![1, 2],
+            };
+            let rec: Query = qs::from_str($input).unwrap();
+            assert_eq!(rec, expected, "{}", $rule);
+            assert_eq!(qs::to_string(&expected).unwrap(), $input, "{}", $rule);
+        }};
+    }
+
+    rename_all_test!(
+        "camelCase",
+        "firstName=Alice&homeAddress[streetName]=Main&homeAddress[postCode]=12345&tagIds[0]=1&tagIds[1]=2"
+    );
+    rename_all_test!(
+        "snake_case",
+        "first_name=Alice&home_address[street_name]=Main&home_address[post_code]=12345&tag_ids[0]=1&tag_ids[1]=2"
+    );
+    rename_all_test!(
+        "SCREAMING_SNAKE_CASE",
+        "FIRST_NAME=Alice&HOME_ADDRESS[STREET_NAME]=Main&HOME_ADDRESS[POST_CODE]=12345&TAG_IDS[0]=1&TAG_IDS[1]=2"
+    );
+    rename_all_test!(
+        "kebab-case",
+        "first-name=Alice&home-address[street-name]=Main&home-address[post-code]=12345&tag-ids[0]=1&tag-ids[1]=2"
+    );
+    rename_all_test!(
+        "PascalCase",
+        "FirstName=Alice&HomeAddress[StreetName]=Main&HomeAddress[PostCode]=12345&TagIds[0]=1&TagIds[1]=2"
+    );
+}
+
+#[test]
+fn deserialize_deny_unknown_fields() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    struct Inner {
+        c: u8,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    struct Query {
+        a: u8,
+        b: Option<Inner>,
+    }
+
+    let rec: Query = qs::from_str("a=1&b[c]=2").unwrap();
+    assert_eq!(
+        rec,
+        Query {
+            a: 1,
+            b: Some(Inner { c: 2 }),
+        }
+    );
+
+    let err = qs::from_str::<Query>("a=1&z=2").unwrap_err();
+    assert!(err.to_string().contains("unknown field `z`"), "{}", err);
+
+    let err = qs::from_str::<Query>("a=1&b[c]=2&b[d]=3").unwrap_err();
+    assert!(err.to_string().contains("unknown field `d`"), "{}", err);
+}
+
+#[test]
+fn deserialize_field_defaults() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Pagination {
+        #[serde(default = "default_page")]
+        page: u32,
+        #[serde(default)]
+        per_page: Option<u32>,
+        #[serde(default)]
+        sort: Vec<String>,
+    }
+
+    fn default_page() -> u32 {
+        1
+    }
+
+    let rec: Pagination = qs::from_str("").unwrap();
+    assert_eq!(
+        rec,
+        Pagination {
+            page: 1,
+            per_page: None,
+            sort: vec![],
+        }
+    );
+
+    let rec: Pagination = qs::from_str("page=3&sort[]=name").unwrap();
+    assert_eq!(
+        rec,
+        Pagination {
+            page: 3,
+            per_page: None,
+            sort: vec!["name".to_owned()],
+        }
+    );
+}
+
+#[test]
+fn deserialize_empty_struct() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Empty {}
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    struct EmptyDenyUnknown {}
+
+    // `strict` only concerns brackets, so unknown keys are ignored in both
+    // modes unless the struct denies them.
+    for &strict in &[true, false] {
+        let config = qs::Config::new(5, strict);
+        assert_eq!(config.deserialize_str::<Empty>("").unwrap(), Empty {});
+        assert_eq!(config.deserialize_str::<Empty>("a=1").unwrap(), Empty {});
+        assert_eq!(
+            config.deserialize_str::<EmptyDenyUnknown>("").unwrap(),
+            EmptyDenyUnknown {}
+        );
+        let err = config
+            .deserialize_str::<EmptyDenyUnknown>("a=1")
+            .unwrap_err();
+        assert!(err.to_string().contains("unknown field `a`"), "{}", err);
+    }
+}
+
+#[test]
+fn deserialize_repeated_keys() {
+    use qs::{ArrayFormat, Config, RepeatedKeys};
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Query {
+        color: Vec<String>,
+        size: Vec<u8>,
+        #[serde(default)]
+        filter: HashMap<String, Vec<u8>>,
+    }
+
+    let rec: Query =
+        qs::from_str("color=red&size=1&color=blue&filter[a]=1&filter[a]=2&color=&size=3").unwrap();
+    assert_eq!(rec.color, ["red", "blue", ""]);
+    assert_eq!(rec.size, [1, 3]);
+    assert_eq!(rec.filter["a"], [1, 2]);
+
+    // Repeated keys may be mixed with unindexed ones.
+    let rec: Query = qs::from_str("color=red&color[]=blue&size[]=1&size=2").unwrap();
+    assert_eq!(rec.color, ["red", "blue"]);
+    assert_eq!(rec.size, [1, 2]);
+
+    let config = Config::default().array_format(ArrayFormat::Repeat);
+    let encoded = config.serialize_string(&rec).unwrap();
+    assert_eq!(encoded, "color=red&color=blue&size=1&size=2");
+    assert_eq!(qs::from_str::<Query>(&encoded).unwrap(), rec);
+
+    // Only flat values are collected.
+    qs::from_str::<HashMap<String, Vec<String>>>("a[b]=1&a[b]=2&a=3").expect_err("nested");
+
+    Config::default()
+        .array_limit(1)
+        .deserialize_str::<Query>("color=a&color=b&color=c")
+        .expect_err("above the array limit");
+    Config::default()
+        .repeated_keys(RepeatedKeys::Error)
+        .deserialize_str::<Query>("color=red&color=blue")
+        .expect_err("repeated key");
+}
+
+#[test]
+fn deserialize_errors_use_expecting() {
+    use serde::de::{self, Deserializer, Visitor};
+    use std::fmt;
+
+    #[derive(Debug)]
+    struct Port(u16);
+
+    impl<'de> de::Deserialize<'de> for Port {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct PortVisitor;
+
+            impl<'de> Visitor<'de> for PortVisitor {
+                type Value = Port;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("a port number")
+                }
+
+                fn visit_u16<E: de::Error>(self, v: u16) -> Result<Port, E> {
+                    Ok(Port(v))
+                }
+            }
+
+            deserializer.deserialize_u16(PortVisitor)
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Query {
+        #[allow(dead_code)]
+        port: Port,
+    }
+
+    let rec: Query = qs::from_str("port=8080").unwrap();
+    assert_eq!(rec.port.0, 8080);
+
+    let err = qs::from_str::<Query>("port[a]=1").unwrap_err();
+    assert_eq!(err.to_string(), "invalid type: map, expected a port number");
+    let err = qs::from_str::<Query>("port[]=1").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid type: sequence, expected a port number"
+    );
+
+    #[derive(Debug, Deserialize)]
+    struct Numbers {
+        #[allow(dead_code)]
+        n: u8,
+    }
+
+    let err = qs::from_str::<Numbers>("n[0]=1").unwrap_err();
+    assert_eq!(err.to_string(), "invalid type: sequence, expected u8");
+}
+
+#[test]
+fn deserialize_sets() {
+    use std::collections::{BTreeSet, HashSet};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        tags: HashSet<String>,
+        ids: BTreeSet<u8>,
+    }
+
+    let rec: Query = qs::from_str("tags[]=a&tags[]=b&tags[]=a&ids[0]=3&ids[1]=1&ids[2]=3").unwrap();
+    assert_eq!(rec.tags, ["a", "b"].iter().map(|&s| s.to_owned()).collect());
+    assert_eq!(rec.ids.into_iter().collect::<Vec<_>>(), [1, 3]);
+
+    // Repeated keys and single values are collected too.
+    let rec: Query = qs::from_str("tags=x&tags=x&ids=7").unwrap();
+    assert_eq!(rec.tags.len(), 1);
+    assert!(rec.tags.contains("x"));
+    assert_eq!(rec.ids.into_iter().collect::<Vec<_>>(), [7]);
+
+    qs::from_str::<Query>("tags[]=a&ids[]=x").expect_err("invalid element");
+}
+
+#[test]
+fn deserialize_pairs() {
+    use qs::{Config, ErrorPolicy};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        name: String,
+        address: Address,
+        ids: Vec<u8>,
+        tags: Vec<String>,
+        #[serde(default)]
+        color: Vec<String>,
+    }
+
+    let input = "name=Acme&address[city]=Carrot+City&address[postcode]=12345&\
+                 ids[1]=2&ids[0]=1&tags[]=a&tags[]=b&color=red&color=blue";
+    let pairs = vec![
+        ("name", "Acme"),
+        ("address[city]", "Carrot City"),
+        ("address[postcode]", "12345"),
+        ("ids[1]", "2"),
+        ("ids[0]", "1"),
+        ("tags[]", "a"),
+        ("tags[]", "b"),
+        ("color", "red"),
+        ("color", "blue"),
+    ];
+    let rec: Query = qs::from_pairs(pairs.clone()).unwrap();
+    assert_eq!(rec, qs::from_str::<Query>(input).unwrap());
+    assert_eq!(rec.ids, [1, 2]);
+
+    // Owned pairs are accepted too, and are not decoded again.
+    let owned = vec![
+        ("name".to_owned(), "A%26B+C".to_owned()),
+        ("address[city]".to_owned(), "x&y=z".to_owned()),
+        ("address[postcode]".to_owned(), String::new()),
+        ("ids[0]".to_owned(), "1".to_owned()),
+        ("tags[]".to_owned(), "a".to_owned()),
+    ];
+    let rec: Query = qs::from_pairs(owned).unwrap();
+    assert_eq!(rec.name, "A%26B+C");
+    assert_eq!(rec.address.city, "x&y=z");
+    assert_eq!(rec.tags, ["a"]);
+
+    let config = Config::default().max_depth(0);
+    let rec: HashMap<String, String> = config.deserialize_pairs(vec![("a[b]", "1")]).unwrap();
+    assert_eq!(rec["a[b]"], "1");
+
+    Config::default()
+        .array_limit(1)
+        .deserialize_pairs::<Query, _, _, _>(vec![("ids[5]", "1")])
+        .expect_err("above the array limit");
+    Config::default()
+        .key_length_limit(3)
+        .deserialize_pairs::<HashMap<String, String>, _, _, _>(vec![("long", "1")])
+        .expect_err("key too long");
+    qs::from_pairs::<Query, _, _, _>(vec![("tags[][a]", "1")]).expect_err("unindexed structs");
+
+    // Text after a closing bracket is rejected as when parsing, or skipped
+    // when not strict.
+    let err = qs::from_pairs::<HashMap<String, HashMap<String, String>>, _, _, _>(vec![
+        ("x", "1"),
+        ("a[b]c", "1"),
+    ])
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "parsing failed with error: 'Unexpected character: 'c' found when parsing' at position: 1"
+    );
+    qs::from_str::<HashMap<String, HashMap<String, String>>>("a[b]c=1").expect_err("text after ]");
+    let rec: HashMap<String, HashMap<String, String>> = Config::new(5, false)
+        .deserialize_pairs(vec![("a[b]c", "1")])
+        .unwrap();
+    assert_eq!(rec["a"]["b"], "1");
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Page {
+        #[serde(default)]
+        page: u32,
+        q: String,
+    }
+
+    let rec: Page = Config::default()
+        .on_error(ErrorPolicy::Default)
+        .deserialize_pairs(vec![("page", "x"), ("q", "rust")])
+        .unwrap();
+    assert_eq!(
+        rec,
+        Page {
+            page: 0,
+            q: "rust".to_owned(),
+        }
+    );
+}
+
+#[test]
+fn deserialize_multi_value_map() {
+    let rec: HashMap<String, Vec<String>> = qs::from_str("a=1&b=2&a=3").unwrap();
+    let mut expected = HashMap::new();
+    expected.insert("a".to_owned(), vec!["1".to_owned(), "3".to_owned()]);
+    expected.insert("b".to_owned(), vec!["2".to_owned()]);
+    assert_eq!(rec, expected);
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        tag: Vec<String>,
+        id: Vec<u32>,
+    }
+
+    // A key which appears once is a sequence of one element.
+    let rec: Query = qs::from_str("tag=x&id=7").unwrap();
+    assert_eq!(
+        rec,
+        Query {
+            tag: vec!["x".to_owned()],
+            id: vec![7],
+        }
+    );
+    let rec: Query = qs::from_str("tag=&id=7&id=8").unwrap();
+    assert_eq!(rec.tag, [""]);
+    assert_eq!(rec.id, [7, 8]);
+}
+
+#[test]
+fn deserialize_key_order() {
+    // Records the keys of a map in the order they are visited.
+    struct Keys(Vec<String>);
+
+    impl<'de> serde::Deserialize<'de> for Keys {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct KeysVisitor;
+
+            impl<'de> serde::de::Visitor<'de> for KeysVisitor {
+                type Value = Keys;
+
+                fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    f.write_str("a map")
+                }
+
+                fn visit_map<A: serde::de::MapAccess<'de>>(
+                    self,
+                    mut map: A,
+                ) -> Result<Keys, A::Error> {
+                    let mut keys = Vec::new();
+                    while let Some((key, _)) = map.next_entry::<String, serde::de::IgnoredAny>()? {
+                        keys.push(key);
+                    }
+                    Ok(Keys(keys))
+                }
+            }
+
+            deserializer.deserialize_map(KeysVisitor)
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct Query {
+        z: Keys,
+    }
+
+    let keys: Keys = qs::from_str("b=2&c=3&a=1&z[y]=1").unwrap();
+    assert_eq!(keys.0, ["a", "b", "c", "z"]);
+
+    let query: Query = qs::from_str("z[y]=1&z[x]=2&z[w]=3").unwrap();
+    assert_eq!(query.z.0, ["w", "x", "y"]);
+
+    // Repeated keys keep the position of their first occurrence.
+    let config = qs::Config::default().key_order(qs::KeyOrder::Insertion);
+    let keys: Keys = config.deserialize_str("b=2&c=3&a=1&b=4").unwrap();
+    assert_eq!(keys.0, ["b", "c", "a"]);
+
+    let query: Query = config.deserialize_str("z[y]=1&z[x]=2&z[w]=3").unwrap();
+    assert_eq!(query.z.0, ["y", "x", "w"]);
+
+    let keys: Keys = config
+        .deserialize_pairs(vec![("c", "1"), ("a[b]", "2"), ("b", "3")])
+        .unwrap();
+    assert_eq!(keys.0, ["c", "a", "b"]);
+}
+
+#[test]
+fn deserialize_transparent_map() {
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    #[serde(transparent)]
+    struct Params(BTreeMap<String, String>);
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Params2(HashMap<String, String>);
+
+    let input = "a=1&b=x+y";
+    let params: Params = qs::from_str(input).unwrap();
+    let inner: BTreeMap<String, String> = qs::from_str(input).unwrap();
+    assert_eq!(params.0, inner);
+    assert_eq!(
+        qs::to_string(&params).unwrap(),
+        qs::to_string(&inner).unwrap()
+    );
+
+    let params: Params2 = qs::from_str(input).unwrap();
+    assert_eq!(params.0["b"], "x y");
+    assert_eq!(qs::from_str::<Params2>("").unwrap().0.len(), 0);
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Inner {
+        a: Option<u8>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Wrapper(Inner);
+
+    let config = qs::Config::default().on_error(qs::ErrorPolicy::Skip);
+    assert_eq!(
+        config.deserialize_str::<Wrapper>("a=x").unwrap(),
+        Wrapper(Inner { a: None })
+    );
+}
+
+#[test]
+fn deserialize_aliased_fields() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        #[serde(alias = "query")]
+        q: String,
+        #[serde(default = "first_page")]
+        page: u32,
+    }
+
+    fn first_page() -> u32 {
+        1
+    }
+
+    let expected = Query {
+        q: "rust".to_owned(),
+        page: 1,
+    };
+    assert_eq!(qs::from_str::<Query>("q=rust").unwrap(), expected);
+    assert_eq!(qs::from_str::<Query>("query=rust").unwrap(), expected);
+    for &policy in &[qs::ErrorPolicy::Skip, qs::ErrorPolicy::Default] {
+        let config = qs::Config::default().on_error(policy);
+        assert_eq!(
+            config.deserialize_str::<Query>("query=rust").unwrap(),
+            expected
+        );
+    }
+}
@@ -0,0 +1,67 @@
+//! Counts allocations to check that map keys are serialized without an
+//! intermediate `String`. This is its own test binary, as the allocator is
+//! global.
+
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_qs as qs;
+
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn allocations<T: Serialize>(value: &T) -> usize {
+    let mut buffer = Vec::with_capacity(64);
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    qs::to_writer(value, &mut buffer).unwrap();
+    let count = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    assert_eq!(buffer, b"key=value");
+    count
+}
+
+#[test]
+fn map_keys_allocate_like_struct_fields() {
+    #[derive(Serialize)]
+    struct Struct {
+        key: &'static str,
+    }
+
+    /// Serializes its entry with separate `serialize_key` and
+    /// `serialize_value` calls.
+    struct KeyThenValue;
+
+    impl Serialize for KeyThenValue {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut map = serializer.serialize_map(Some(1))?;
+            map.serialize_key("key")?;
+            map.serialize_value("value")?;
+            map.end()
+        }
+    }
+
+    let mut map = BTreeMap::new();
+    map.insert("key", "value");
+
+    let expected = allocations(&Struct { key: "value" });
+    assert_eq!(allocations(&map), expected);
+    assert_eq!(allocations(&KeyThenValue), expected);
+}
@@ -0,0 +1,128 @@
+extern crate indexmap;
+extern crate serde_qs as qs;
+
+use indexmap::IndexMap;
+use qs::Level;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+/// A level which failed to parse, as a key cannot hold both a map and a value.
+fn invalid() -> Level<'static> {
+    qs::parse_top_level(b"a[b]=1&a=2")
+        .unwrap()
+        .remove("a")
+        .unwrap()
+}
+
+#[test]
+fn display_flat() {
+    assert_eq!(Level::Flat(Cow::Borrowed("abc")).to_string(), "abc");
+    assert_eq!(Level::Flat(Cow::Borrowed("a b&c")).to_string(), "a+b%26c");
+}
+
+#[test]
+fn display_nested() {
+    let mut inner = IndexMap::new();
+    inner.insert(Cow::Borrowed("c"), Level::Flat(Cow::Borrowed("2")));
+    inner.insert(
+        Cow::Borrowed("d"),
+        Level::Sequence(vec![
+            Level::Flat(Cow::Borrowed("x")),
+            Level::Flat(Cow::Borrowed("y")),
+        ]),
+    );
+    let mut ordered = BTreeMap::new();
+    ordered.insert(3, Level::Flat(Cow::Borrowed("z")));
+
+    let mut map = IndexMap::new();
+    map.insert(Cow::Borrowed("a"), Level::Flat(Cow::Borrowed("1")));
+    map.insert(Cow::Borrowed("b"), Level::Nested(inner));
+    map.insert(Cow::Borrowed("e"), Level::OrderedSeq(ordered));
+
+    assert_eq!(
+        Level::Nested(map).to_string(),
+        "a=1&b[c]=2&b[d][0]=x&b[d][1]=y&e[3]=z"
+    );
+}
+
+#[test]
+fn display_sequence() {
+    let seq = Level::Sequence(vec![
+        Level::Flat(Cow::Borrowed("x")),
+        invalid(),
+        Level::Flat(Cow::Borrowed("y")),
+    ]);
+    assert_eq!(seq.to_string(), "0=x&2=y");
+}
+
+#[test]
+fn parse_top_level() {
+    let map = qs::parse_top_level(b"a=1&b[c]=2&b[d][]=3&e[0]=x&f%20g=h").unwrap();
+    assert_eq!(map.len(), 4);
+    match map["a"] {
+        Level::Flat(ref value) => assert_eq!(value, "1"),
+        ref other => panic!("expected a flat level, got {:?}", other),
+    }
+    assert!(matches!(map["b"], Level::Nested(_)));
+    assert_eq!(map["b"].to_string(), "c=2&d[0]=3");
+    assert!(matches!(map["e"], Level::OrderedSeq(_)));
+    assert!(matches!(map["f g"], Level::Flat(_)));
+
+    assert!(qs::parse_top_level(b"").unwrap().is_empty());
+}
+
+#[test]
+fn traverse_events() {
+    #[derive(Default)]
+    struct Events(Vec<String>);
+
+    impl qs::LevelVisitor for Events {
+        fn visit_flat(&mut self, key: &str, value: &str) {
+            self.0.push(format!("flat {}={}", key, value));
+        }
+
+        fn visit_nested_start(&mut self, key: &str) {
+            self.0.push(format!("start {}", key));
+        }
+
+        fn visit_nested_end(&mut self, key: &str) {
+            self.0.push(format!("end {}", key));
+        }
+
+        fn visit_seq_element(&mut self, key: &str, index: usize) {
+            self.0.push(format!("element {} {}", key, index));
+        }
+    }
+
+    let mut ordered = BTreeMap::new();
+    ordered.insert(2, Level::Flat(Cow::Borrowed("x")));
+    let mut inner = IndexMap::new();
+    inner.insert(Cow::Borrowed("c"), Level::OrderedSeq(ordered));
+    inner.insert(Cow::Borrowed("d"), invalid());
+    let mut map = IndexMap::new();
+    map.insert(Cow::Borrowed("a b"), Level::Flat(Cow::Borrowed("1")));
+    map.insert(
+        Cow::Borrowed("b"),
+        Level::Sequence(vec![Level::Nested(inner)]),
+    );
+
+    let mut events = Events::default();
+    qs::traverse(&Level::Nested(map), "", &mut events);
+    assert_eq!(
+        events.0,
+        [
+            "start ",
+            "flat a b=1",
+            "element b 0",
+            "start b[0]",
+            "element b[0][c] 2",
+            "flat b[0][c][2]=x",
+            "end b[0]",
+            "end ",
+        ]
+    );
+
+    let mut events = Events::default();
+    qs::traverse(&Level::Flat(Cow::Borrowed("v")), "k", &mut events);
+    assert_eq!(events.0, ["flat k=v"]);
+}
@@ -0,0 +1,64 @@
+#![cfg(feature = "level-serde")]
+
+extern crate indexmap;
+extern crate serde_json;
+extern crate serde_qs as qs;
+
+use indexmap::IndexMap;
+use qs::Level;
+use serde_json::json;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+/// A level which failed to parse, as a key cannot hold both a map and a value.
+fn invalid() -> Level<'static> {
+    qs::parse_top_level(b"a[b]=1&a=2")
+        .unwrap()
+        .remove("a")
+        .unwrap()
+}
+
+#[test]
+fn level_to_json() {
+    let mut a = IndexMap::new();
+    a.insert(Cow::Borrowed("b"), Level::Flat(Cow::Borrowed("1")));
+    let mut c = BTreeMap::new();
+    c.insert(0, Level::Flat(Cow::Borrowed("x")));
+    c.insert(1, Level::Flat(Cow::Borrowed("y")));
+    a.insert(Cow::Borrowed("c"), Level::OrderedSeq(c));
+
+    let mut map = IndexMap::new();
+    map.insert(Cow::Borrowed("a"), Level::Nested(a));
+    map.insert(
+        Cow::Borrowed("d"),
+        Level::Sequence(vec![Level::Flat(Cow::Borrowed("2"))]),
+    );
+    map.insert(Cow::Borrowed("e"), Level::Flat(Cow::Borrowed("")));
+    let level = Level::Nested(map);
+
+    assert_eq!(
+        serde_json::to_value(&level).unwrap(),
+        json!({
+            "a": { "b": "1", "c": ["x", "y"] },
+            "d": ["2"],
+            "e": "",
+        })
+    );
+
+    assert_eq!(serde_json::to_value(invalid()).unwrap(), json!(null));
+}
+
+#[test]
+fn level_from_json() {
+    let level: Level = serde_json::from_value(json!({
+        "a": { "b": "1", "c": ["x", 2, true] },
+        "d": null,
+    }))
+    .unwrap();
+    assert_eq!(level.to_string(), "a[b]=1&a[c][0]=x&a[c][1]=2&a[c][2]=true");
+
+    match serde_json::from_value::<Level>(json!("abc")).unwrap() {
+        Level::Flat(Cow::Owned(s)) => assert_eq!(s, "abc"),
+        other => panic!("expected a flat level, got {:?}", other),
+    }
+}
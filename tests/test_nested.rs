@@ -0,0 +1,49 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate serde_qs as qs;
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+struct Address {
+    city: String,
+    postcode: String,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+struct Filter {
+    ids: Vec<u8>,
+    address: Address,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+struct Request {
+    name: String,
+    #[serde(with = "qs::nested")]
+    filter: Filter,
+}
+
+#[test]
+fn nested_json_roundtrip() {
+    let request = Request {
+        name: "search".to_owned(),
+        filter: Filter {
+            ids: vec![1, 2],
+            address: Address {
+                city: "Carrot City".to_owned(),
+                postcode: "12345".to_owned(),
+            },
+        },
+    };
+    let json = r#"{"name":"search","filter":"ids[0]=1&ids[1]=2&address[city]=Carrot+City&address[postcode]=12345"}"#;
+
+    assert_eq!(serde_json::to_string(&request).unwrap(), json);
+    assert_eq!(serde_json::from_str::<Request>(json).unwrap(), request);
+}
+
+#[test]
+fn nested_json_errors() {
+    serde_json::from_str::<Request>(r#"{"name":"x","filter":"ids[0]=a"}"#)
+        .expect_err("invalid nested querystring");
+    serde_json::from_str::<Request>(r#"{"name":"x","filter":{"ids":[1]}}"#)
+        .expect_err("nested value must be a string");
+}
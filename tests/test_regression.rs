@@ -19,3 +19,94 @@ fn double_encoding_keys() {
     print!("{}", encoded);
     assert_eq!(serde_qs::from_str::<Human>(&encoded).unwrap(), human);
 }
+
+#[test]
+fn unindexed_sequence_values() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        a: Vec<String>,
+        b: String,
+    }
+
+    let rec: Query = qs::from_str("a[]=first&a[]=second%20value&a[]=&b=last").unwrap();
+    assert_eq!(
+        rec,
+        Query {
+            a: vec!["first".to_owned(), "second value".to_owned(), "".to_owned()],
+            b: "last".to_owned(),
+        }
+    );
+
+    let rec: Query = qs::from_str("b=x&a[]=12345&a[]=abc").unwrap();
+    assert_eq!(rec.a, vec!["12345", "abc"]);
+}
+
+#[test]
+fn peeked_bytes_after_keys() {
+    // The byte following each key segment is peeked to determine the key
+    // type, and must not be lost once the segment has been split off.
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Inner {
+        b: u8,
+        c: Vec<u8>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Query {
+        a: Inner,
+        d: String,
+    }
+
+    let expected = Query {
+        a: Inner {
+            b: 1,
+            c: vec![2, 3],
+        },
+        d: "".to_owned(),
+    };
+
+    let rec: Query = qs::from_str("a[b]=1&a[c][]=2&a[c][]=3&d=").unwrap();
+    assert_eq!(rec, expected);
+
+    let config = qs::Config::new(5, false);
+    let rec: Query = config
+        .deserialize_str("a%5Bb%5D=1&a%5Bc%5D%5B%5D=2&a%5Bc%5D%5B%5D=3&d")
+        .unwrap();
+    assert_eq!(rec, expected);
+}
+
+#[test]
+fn key_value_separator_consumed_once() {
+    // The `=` ending a key is handed back to the parser as the peeked byte,
+    // and must neither end up in the value nor be read a second time.
+    use std::collections::HashMap;
+
+    let map: HashMap<String, String> = qs::from_str("a==b&c=d=e&f=&g").unwrap();
+    assert_eq!(map["a"], "=b");
+    assert_eq!(map["c"], "d=e");
+    assert_eq!(map["f"], "");
+    assert_eq!(map["g"], "");
+
+    let map: HashMap<String, HashMap<String, String>> =
+        qs::from_str("a[b]==x&a[c=d]=y&e[f]=").unwrap();
+    assert_eq!(map["a"]["b"], "=x");
+    assert_eq!(map["a"]["c=d"], "y");
+    assert_eq!(map["e"]["f"], "");
+}
+
+#[test]
+fn malformed_input_does_not_panic() {
+    // Truncated or unbalanced keys must be reported as errors, since the
+    // input is usually user-controlled.
+    use std::collections::HashMap;
+
+    for &input in &["a[", "[", "a[]]=1", "a[][b", "a=1&a[b]=2", "a[]=1&a[b]=2"] {
+        for &strict in &[true, false] {
+            let config = qs::Config::new(5, strict);
+            config
+                .deserialize_str::<HashMap<String, String>>(input)
+                .expect_err(input);
+        }
+    }
+    qs::from_str::<HashMap<String, String>>("a[b]c=1").expect_err("strict");
+}
@@ -0,0 +1,51 @@
+#![cfg(feature = "schemars")]
+
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_qs as qs;
+
+use schemars::{schema_for, JsonSchema};
+
+#[allow(dead_code)]
+#[derive(Deserialize, JsonSchema)]
+enum Sort {
+    Asc,
+    Desc,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize, JsonSchema)]
+struct Filter {
+    tags: Vec<String>,
+    min: Option<u32>,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize, JsonSchema)]
+struct Query {
+    filter: Filter,
+    sort: Sort,
+}
+
+#[test]
+fn value_schema() {
+    let schema = serde_json::to_value(schema_for!(qs::QsValue)).unwrap();
+    let any_of = schema["anyOf"].as_array().unwrap();
+    assert_eq!(any_of.len(), 3);
+    assert_eq!(any_of[0]["type"], "string");
+    assert_eq!(any_of[1]["type"], "object");
+    assert_eq!(any_of[2]["type"], "array");
+}
+
+#[cfg(feature = "axum")]
+#[test]
+fn axum_query_schema() {
+    assert_eq!(schema_for!(qs::axum::QsQuery<Query>), schema_for!(Query));
+}
+
+#[cfg(any(feature = "actix4", feature = "actix3"))]
+#[test]
+fn actix_schema() {
+    assert_eq!(schema_for!(qs::actix::QsQuery<Query>), schema_for!(Query));
+    assert_eq!(schema_for!(qs::actix::QsForm<Query>), schema_for!(Query));
+}
@@ -276,3 +276,975 @@ fn test_serializer_unit() {
 
     assert_eq!(writer, b"t=", "we are testing B{{t: ()}}");
 }
+
+#[test]
+fn serialize_sorted() {
+    use std::collections::HashMap;
+
+    #[derive(Serialize)]
+    struct Query {
+        b: u8,
+        a: HashMap<String, u8>,
+        c: Vec<u8>,
+    }
+
+    let query = Query {
+        b: 3,
+        a: vec![("y".to_owned(), 2), ("x".to_owned(), 1)]
+            .into_iter()
+            .collect(),
+        c: (0..12).collect(),
+    };
+
+    assert_eq!(
+        qs::to_sorted_string(&query).unwrap(),
+        "a[x]=1&a[y]=2&b=3&\
+         c[0]=0&c[1]=1&c[2]=2&c[3]=3&c[4]=4&c[5]=5&c[6]=6&\
+         c[7]=7&c[8]=8&c[9]=9&c[10]=10&c[11]=11"
+    );
+}
+
+#[test]
+fn serialize_deterministic() {
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Serialize)]
+    struct Query {
+        a: HashMap<String, u8>,
+        b: BTreeMap<String, u8>,
+    }
+
+    let pairs = (0..32).map(|i| (format!("k{}", i), i));
+    let query = Query {
+        a: pairs.clone().collect(),
+        b: pairs.clone().collect(),
+    };
+    assert_eq!(
+        qs::to_string(&query).unwrap(),
+        qs::to_string(&query).unwrap()
+    );
+
+    // Equal maps built in a different order are only guaranteed to give the
+    // same sorted querystring.
+    let mut other = HashMap::with_capacity(256);
+    other.extend(pairs.rev());
+    let other = Query {
+        a: other,
+        b: query.b.clone(),
+    };
+    assert_eq!(query.a, other.a);
+    assert_eq!(
+        qs::to_sorted_string(&query).unwrap(),
+        qs::to_sorted_string(&other).unwrap()
+    );
+}
+
+#[test]
+fn serialize_custom_bound() {
+    #[derive(Serialize)]
+    #[serde(bound = "T: serde::Serialize")]
+    struct Paginated<T> {
+        items: Vec<T>,
+        page: u32,
+    }
+
+    let page = Paginated {
+        items: vec![Address {
+            city: "Carrot City".to_string(),
+            street: "Main Street".to_string(),
+            postcode: "12345".to_string(),
+        }],
+        page: 2,
+    };
+
+    assert_eq!(
+        qs::to_string(&page).unwrap(),
+        "items[0][city]=Carrot+City&items[0][street]=Main+Street&\
+         items[0][postcode]=12345&page=2"
+    );
+}
+
+#[test]
+fn serialize_max_depth() {
+    #[derive(Serialize)]
+    struct Tree {
+        value: u8,
+        children: Vec<Tree>,
+    }
+
+    fn chain(depth: u8) -> Tree {
+        Tree {
+            value: depth,
+            children: if depth == 0 {
+                vec![]
+            } else {
+                vec![chain(depth - 1)]
+            },
+        }
+    }
+
+    // Every level of the tree adds two brackets: the field and the index.
+    assert_eq!(
+        qs::to_string(&chain(2)).unwrap(),
+        "value=2&children[0][value]=1&children[0][children][0][value]=0"
+    );
+    // The parser's `max_depth` does not apply to serializing.
+    assert!(qs::to_string(&chain(3)).is_ok());
+    assert!(qs::Config::new(1, true).serialize_string(&chain(3)).is_ok());
+    qs::to_string(&chain(100)).expect_err("exceeds the default limit of 128");
+
+    let config = qs::Config::default().serialize_depth_limit(6);
+    assert!(config.serialize_string(&chain(3)).is_ok());
+    config
+        .serialize_string(&chain(4))
+        .expect_err("deep trees are rejected");
+
+    let mut buffer = Vec::new();
+    qs::Config::default()
+        .serialize_depth_limit(1)
+        .serialize_to_writer(&chain(1), &mut buffer)
+        .expect_err("exceeds a depth of 1");
+
+    #[derive(Serialize)]
+    struct Node {
+        value: u8,
+        next: Option<Box<Node>>,
+    }
+
+    let mut list = None;
+    for value in 0..6 {
+        list = Some(Box::new(Node { value, next: list }));
+    }
+    assert_eq!(
+        qs::to_string(&list).unwrap(),
+        "value=5&next[value]=4&next[next][value]=3&next[next][next][value]=2&\
+         next[next][next][next][value]=1&next[next][next][next][next][value]=0"
+    );
+}
+
+#[test]
+fn serialize_transparent() {
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    #[serde(transparent)]
+    struct UserId(String);
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    #[serde(transparent)]
+    struct Limit {
+        value: u64,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Query {
+        id: UserId,
+        ids: Vec<UserId>,
+        limit: Limit,
+    }
+
+    let query = Query {
+        id: UserId("abc".to_owned()),
+        ids: vec![UserId("d".to_owned()), UserId("e".to_owned())],
+        limit: Limit { value: 10 },
+    };
+    let params = "id=abc&ids[0]=d&ids[1]=e&limit=10";
+    assert_eq!(qs::to_string(&query).unwrap(), params);
+    assert_eq!(qs::from_str::<Query>(params).unwrap(), query);
+}
+
+#[test]
+fn serialize_encoding_set() {
+    use qs::{AsciiSet, QS_ENCODE_SET};
+
+    const STRICT_SET: &AsciiSet = &QS_ENCODE_SET.add(b'*').add(b' ');
+    const PATH_SET: &AsciiSet = &QS_ENCODE_SET.remove(b'/').remove(b'~');
+
+    #[derive(Serialize)]
+    struct Query {
+        path: String,
+        pattern: String,
+        tags: Vec<&'static str>,
+    }
+
+    let q = Query {
+        path: "~user/docs".to_owned(),
+        pattern: "a* b".to_owned(),
+        tags: vec!["x/y"],
+    };
+
+    assert_eq!(
+        qs::to_string(&q).unwrap(),
+        "path=%7Euser%2Fdocs&pattern=a*+b&tags[0]=x%2Fy"
+    );
+
+    let config = qs::Config::default().encoding_set(STRICT_SET);
+    assert_eq!(
+        config.serialize_string(&q).unwrap(),
+        "path=%7Euser%2Fdocs&pattern=a%2A%20b&tags[0]=x%2Fy"
+    );
+
+    let config = qs::Config::default().encoding_set(PATH_SET);
+    assert_eq!(
+        config.serialize_string(&q).unwrap(),
+        "path=~user/docs&pattern=a*+b&tags[0]=x/y"
+    );
+}
+
+#[test]
+fn serialize_with_timestamp() {
+    use std::collections::HashMap;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[derive(Serialize)]
+    struct Query {
+        page: u32,
+    }
+
+    fn now() -> u128 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis()
+    }
+
+    let before = now();
+    let output = qs::to_string_with_timestamp(&Query { page: 2 }).unwrap();
+    let after = now();
+
+    let map: HashMap<String, String> = qs::from_str(&output).unwrap();
+    assert_eq!(map["page"], "2");
+    let ts: u128 = map["_ts"].parse().unwrap();
+    assert!(before <= ts && ts <= after);
+
+    let output = qs::to_string_with_timestamp_key(&Query { page: 2 }, "cache buster").unwrap();
+    assert!(output.starts_with("page=2&cache+buster="));
+
+    let empty: HashMap<String, u8> = HashMap::new();
+    let output = qs::to_string_with_timestamp(&empty).unwrap();
+    assert!(output.starts_with("_ts="));
+}
+
+#[test]
+fn replace_field() {
+    #[derive(Serialize)]
+    struct Range {
+        min: u32,
+        max: u32,
+    }
+
+    assert_eq!(
+        qs::replace("page=1&sort=name", "page", &2).unwrap(),
+        "page=2&sort=name"
+    );
+    assert_eq!(
+        qs::replace("sort=name", "page", &2).unwrap(),
+        "sort=name&page=2"
+    );
+    assert_eq!(qs::replace("", "page", &2).unwrap(), "page=2");
+    assert_eq!(
+        qs::replace(
+            "filter[min_price]=5&filter[max_price]=10",
+            "filter.min_price",
+            &100
+        )
+        .unwrap(),
+        "filter[min_price]=100&filter[max_price]=10"
+    );
+    // Encoded keys are matched by their decoded form.
+    assert_eq!(
+        qs::replace("a=1&filter%5Bmin_price%5D=5", "filter.min_price", &"x y").unwrap(),
+        "a=1&filter[min_price]=x+y"
+    );
+    // All pairs below the field are replaced, whatever the new value is.
+    assert_eq!(
+        qs::replace(
+            "price[min]=1&page=3&price[max]=2",
+            "price",
+            &Range { min: 5, max: 6 }
+        )
+        .unwrap(),
+        "price[min]=5&price[max]=6&page=3"
+    );
+    assert_eq!(
+        qs::replace("tags[0]=a&tags[1]=b&page=3", "tags", &vec!["c"]).unwrap(),
+        "tags[0]=c&page=3"
+    );
+    // Unset values remove the field.
+    assert_eq!(
+        qs::replace("page=1&sort=name", "sort", &None::<String>).unwrap(),
+        "page=1"
+    );
+    // Keys sharing a prefix are left alone.
+    assert_eq!(
+        qs::replace("page=1&pages=4", "page", &2).unwrap(),
+        "page=2&pages=4"
+    );
+}
+
+#[test]
+fn serialize_filter_keys() {
+    #[derive(Serialize)]
+    struct Address {
+        city: String,
+        internal_code: u32,
+    }
+
+    #[derive(Serialize)]
+    struct Query {
+        name: String,
+        internal_id: u32,
+        address: Address,
+        tags: Vec<&'static str>,
+        #[serde(rename = "a b")]
+        spaced: u8,
+    }
+
+    let q = Query {
+        name: "Alice".to_owned(),
+        internal_id: 7,
+        address: Address {
+            city: "Carrot City".to_owned(),
+            internal_code: 3,
+        },
+        tags: vec!["x", "y"],
+        spaced: 1,
+    };
+
+    assert_eq!(
+        qs::filter_keys(&q, |key| !key.starts_with("internal_")).unwrap(),
+        "name=Alice&address[city]=Carrot+City&address[internal_code]=3&tags[0]=x&tags[1]=y&a+b=1"
+    );
+    assert_eq!(
+        qs::filter_keys(&q, |key| !key.contains("internal_")).unwrap(),
+        "name=Alice&address[city]=Carrot+City&tags[0]=x&tags[1]=y&a+b=1"
+    );
+    assert_eq!(
+        qs::filter_keys(&q, |key| key == "tags[1]" || key == "a b").unwrap(),
+        "tags[1]=y&a+b=1"
+    );
+    assert_eq!(qs::filter_keys(&q, |_| false).unwrap(), "");
+}
+
+#[test]
+fn serialize_json_value() {
+    use serde_json::json;
+
+    let value = json!({
+        "name": "Alice",
+        "age": 24,
+        "height": 1.7,
+        "admin": false,
+        "nickname": null,
+        "tags": ["a", "b c"],
+        "address": {
+            "city": "Carrot City",
+            "geo": { "lat": -1.5, "lng": 2 },
+        },
+    });
+
+    // `serde_json` maps are sorted by key.
+    assert_eq!(
+        qs::to_string(&value).unwrap(),
+        "address[city]=Carrot+City&address[geo][lat]=-1.5&address[geo][lng]=2&\
+         admin=false&age=24&height=1.7&name=Alice&nickname=&tags[0]=a&tags[1]=b+c"
+    );
+
+    let deep = json!({ "a": { "b": [ { "c": { "d": [[ { "e": 1 } ]] } } ] } });
+    assert_eq!(qs::to_string(&deep).unwrap(), "a[b][0][c][d][0][0][e]=1");
+    qs::Config::default()
+        .serialize_depth_limit(5)
+        .serialize_string(&deep)
+        .expect_err("deeper than a limit of 5");
+
+    assert_eq!(
+        qs::to_string(&json!("top-level string")).unwrap(),
+        "top-level+string"
+    );
+}
+
+#[test]
+fn serialize_query_string() {
+    use qs::QueryString;
+
+    #[derive(Serialize)]
+    struct Query {
+        page: u32,
+        name: &'static str,
+    }
+
+    fn build() -> Result<QueryString, qs::Error> {
+        let qs = QueryString::from_value(&Query {
+            page: 2,
+            name: "a b",
+        })?;
+        Ok(qs)
+    }
+
+    let qs = build().unwrap();
+    assert_eq!(qs.as_str(), "page=2&name=a+b");
+    assert_eq!(qs.to_string(), "page=2&name=a+b");
+    assert_eq!(String::from(qs.clone()), "page=2&name=a+b");
+    assert_eq!(qs.into_string(), "page=2&name=a+b");
+
+    assert_eq!(QueryString::from_value(&5).unwrap().as_str(), "5");
+}
+
+#[test]
+fn add_to_query_string() {
+    use qs::QueryString;
+
+    #[derive(Serialize)]
+    struct Address {
+        city: &'static str,
+        zip: u32,
+    }
+
+    #[derive(Serialize)]
+    struct Query {
+        page: u32,
+        tags: Vec<&'static str>,
+        address: Address,
+    }
+
+    let qs = QueryString::from_value(&Query {
+        page: 1,
+        tags: vec!["a", "b"],
+        address: Address {
+            city: "Paris",
+            zip: 75001,
+        },
+    })
+    .unwrap();
+
+    let added = qs.clone() + "?page=2&extra=value";
+    assert_eq!(
+        added.as_str(),
+        "tags[0]=a&tags[1]=b&address[city]=Paris&address[zip]=75001&page=2&extra=value"
+    );
+
+    // Nested keys replace the pairs below them, in any encoding.
+    let mut qs = qs;
+    qs += "tags[]=c&address%5Bcity%5D=Lyon";
+    assert_eq!(
+        qs.as_str(),
+        "page=1&address[zip]=75001&tags[]=c&address%5Bcity%5D=Lyon"
+    );
+    qs += "address=none";
+    assert_eq!(qs.as_str(), "page=1&tags[]=c&address=none");
+    qs += "";
+    assert_eq!(qs.as_str(), "page=1&tags[]=c&address=none");
+
+    assert_eq!((QueryString::default() + "&a=1").as_str(), "a=1");
+}
+
+#[test]
+fn assert_eq_qs_macro() {
+    use std::collections::HashMap;
+
+    #[derive(Serialize)]
+    struct Query {
+        name: &'static str,
+        tags: Vec<u8>,
+        extra: HashMap<&'static str, u8>,
+    }
+
+    let q = Query {
+        name: "Alice",
+        tags: (1..=11).collect(),
+        extra: vec![("b", 2), ("a", 1)].into_iter().collect(),
+    };
+
+    qs::assert_eq_qs!(
+        q,
+        "tags[10]=11&tags[0]=1&tags[1]=2&tags[2]=3&tags[3]=4&tags[4]=5&tags[5]=6\
+         &tags[6]=7&tags[7]=8&tags[8]=9&tags[9]=10&extra[b]=2&extra[a]=1&name=Alice"
+    );
+    let expected = "name=Alice&extra[a]=1&extra[b]=2&tags[0]=1&tags[1]=2&tags[2]=3&tags[3]=4\
+                    &tags[4]=5&tags[5]=6&tags[6]=7&tags[7]=8&tags[8]=9&tags[9]=10&tags[10]=11"
+        .to_owned();
+    qs::assert_eq_qs!(q, expected, "unexpected output for {}", "q");
+
+    let result = std::panic::catch_unwind(|| qs::assert_eq_qs!(q, "name=Bob"));
+    assert!(result.is_err());
+}
+
+#[test]
+fn serialize_top_level_option() {
+    assert_eq!(qs::to_string(&Some("value")).unwrap(), "value");
+    assert_eq!(qs::to_string(&Some("a&b=c")).unwrap(), "a%26b%3Dc");
+    assert_eq!(qs::to_string(&Some(12)).unwrap(), "12");
+    assert_eq!(qs::to_string(&None::<String>).unwrap(), "");
+    assert_eq!(qs::to_string(&Some(Some(1.5))).unwrap(), "1.5");
+
+    #[derive(Serialize)]
+    struct Query {
+        a: u8,
+    }
+    assert_eq!(qs::to_string(&Some(Query { a: 1 })).unwrap(), "a=1");
+    assert_eq!(qs::to_string(&None::<Query>).unwrap(), "");
+}
+
+#[test]
+fn serialize_top_level_bool() {
+    assert_eq!(qs::to_string(&true).unwrap(), "true");
+    assert_eq!(qs::to_string(&false).unwrap(), "false");
+
+    let mut buffer = Vec::new();
+    qs::to_writer(&true, &mut buffer).unwrap();
+    assert_eq!(buffer, b"true");
+}
+
+#[test]
+fn serialize_wrapping() {
+    use std::num::Wrapping;
+
+    assert_eq!(qs::to_string(&Wrapping(42u32)).unwrap(), "42");
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Counter {
+        hits: Wrapping<u32>,
+        delta: Wrapping<i8>,
+    }
+
+    let counter = Counter {
+        hits: Wrapping(u32::MAX) + Wrapping(2),
+        delta: Wrapping(-3),
+    };
+    let encoded = qs::to_string(&counter).unwrap();
+    assert_eq!(encoded, "hits=1&delta=-3");
+    assert_eq!(qs::from_str::<Counter>(&encoded).unwrap(), counter);
+}
+
+#[test]
+fn serialize_for_signing() {
+    use qs::{ArrayFormat, Config};
+    use std::collections::BTreeMap;
+
+    fn signing(pairs: &[(&str, &str)]) -> String {
+        let map: BTreeMap<_, _> = pairs.iter().cloned().collect();
+        qs::to_query_string_for_signing(&map).unwrap()
+    }
+
+    // Test vectors from the AWS Signature Version 4 test suite.
+    assert_eq!(
+        signing(&[("Param2", "value2"), ("Param1", "value1")]),
+        "Param1=value1&Param2=value2"
+    );
+    let unreserved = "-._~0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+    assert_eq!(
+        signing(&[(unreserved, unreserved)]),
+        format!("{}={}", unreserved, unreserved)
+    );
+    assert_eq!(signing(&[("\u{1234}", "bar")]), "%E1%88%B4=bar");
+    assert_eq!(
+        signing(&[("Action", "ListUsers"), ("Version", "2010-05-08")]),
+        "Action=ListUsers&Version=2010-05-08"
+    );
+
+    #[derive(Serialize)]
+    struct Values {
+        #[serde(rename = "Param1")]
+        param1: Vec<&'static str>,
+    }
+
+    let config = Config::default().array_format(ArrayFormat::Repeat);
+    let values = Values {
+        param1: vec!["value2", "Value1"],
+    };
+    assert_eq!(
+        config.serialize_string_for_signing(&values).unwrap(),
+        "Param1=Value1&Param1=value2"
+    );
+
+    // The normalized parameters of RFC 5849, section 3.4.1.3.2.
+    #[derive(Serialize)]
+    struct OAuth {
+        b5: &'static str,
+        a3: Vec<&'static str>,
+        #[serde(rename = "c@")]
+        c_at: &'static str,
+        a2: &'static str,
+        oauth_consumer_key: &'static str,
+        oauth_token: &'static str,
+        oauth_signature_method: &'static str,
+        oauth_timestamp: &'static str,
+        oauth_nonce: &'static str,
+        c2: &'static str,
+    }
+
+    let params = OAuth {
+        b5: "=%3D",
+        a3: vec!["a", "2 q"],
+        c_at: "",
+        a2: "r b",
+        oauth_consumer_key: "9djdj82h48djs9d2",
+        oauth_token: "kkk9d7dh3k39sjv7",
+        oauth_signature_method: "HMAC-SHA1",
+        oauth_timestamp: "137131201",
+        oauth_nonce: "7d8f3e4a",
+        c2: "",
+    };
+    assert_eq!(
+        config.serialize_string_for_signing(&params).unwrap(),
+        "a2=r%20b&a3=2%20q&a3=a&b5=%3D%253D&c%40=&c2=&\
+         oauth_consumer_key=9djdj82h48djs9d2&oauth_nonce=7d8f3e4a&\
+         oauth_signature_method=HMAC-SHA1&oauth_timestamp=137131201&\
+         oauth_token=kkk9d7dh3k39sjv7"
+    );
+
+    // Nested keys keep their brackets, encoded.
+    let config = Config::default().include_leading_question_mark(true);
+    let mut inner = BTreeMap::new();
+    inner.insert("b c", "+");
+    let mut nested = BTreeMap::new();
+    nested.insert("a", inner);
+    assert_eq!(
+        config.serialize_string_for_signing(&nested).unwrap(),
+        "a%5Bb%20c%5D=%2B"
+    );
+}
+
+#[test]
+fn diff_keys() {
+    let (added, removed, changed) = qs::diff_keys(
+        "a=1&b=2&c[d]=3&list[]=x&list[]=y&same[]=1",
+        "b=2&c[d]=4&list[]=y&list[]=x&new%20key=&same[]=1&e[10]=1&e[2]=1",
+    );
+    assert_eq!(added, ["e[2]", "e[10]", "new key"]);
+    assert_eq!(removed, ["a"]);
+    assert_eq!(changed, ["c[d]", "list[]"]);
+
+    // Encodings of the same key or value are equal.
+    let (added, removed, changed) = qs::diff_keys("a%5Bb%5D=x+y&c", "a[b]=x%20y&c=");
+    assert!(added.is_empty());
+    assert!(removed.is_empty());
+    assert!(changed.is_empty());
+
+    let (added, removed, changed) = qs::diff_keys("", "a=1");
+    assert_eq!(added, ["a"]);
+    assert!(removed.is_empty() && changed.is_empty());
+}
+
+#[test]
+fn serialize_linked_list() {
+    use std::collections::LinkedList;
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Query {
+        list: LinkedList<String>,
+        nested: LinkedList<LinkedList<u8>>,
+    }
+
+    let query = Query {
+        list: ["a", "b c"].iter().map(|s| s.to_string()).collect(),
+        nested: vec![vec![1, 2].into_iter().collect(), LinkedList::new()]
+            .into_iter()
+            .collect(),
+    };
+    let encoded = qs::to_string(&query).unwrap();
+    assert_eq!(
+        encoded,
+        "list[0]=a&list[1]=b+c&nested[0][0]=1&nested[0][1]=2"
+    );
+
+    // Empty sequences produce no pairs, so the trailing empty list is lost.
+    let rec: Query = qs::from_str(&encoded).unwrap();
+    assert_eq!(rec.list, query.list);
+    assert_eq!(rec.nested.len(), 1);
+    assert_eq!(rec.nested.front(), query.nested.front());
+}
+
+#[test]
+fn serialize_array_format() {
+    use qs::{ArrayFormat, Config};
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Query {
+        ids: Vec<u8>,
+        pair: (String, String),
+        #[serde(default)]
+        empty: Vec<u8>,
+    }
+
+    let q = Query {
+        ids: vec![1, 2],
+        pair: ("a".to_owned(), "b c".to_owned()),
+        empty: vec![],
+    };
+
+    let config = Config::default();
+    assert_eq!(
+        config.serialize_string(&q).unwrap(),
+        "ids[0]=1&ids[1]=2&pair[0]=a&pair[1]=b+c"
+    );
+
+    let config = config.array_format(ArrayFormat::Brackets);
+    let encoded = config.serialize_string(&q).unwrap();
+    assert_eq!(encoded, "ids[]=1&ids[]=2&pair[]=a&pair[]=b+c");
+    assert_eq!(qs::from_str::<Query>(&encoded).unwrap(), q);
+
+    let config = config.array_format(ArrayFormat::Repeat);
+    let encoded = config.serialize_string(&q).unwrap();
+    assert_eq!(encoded, "ids=1&ids=2&pair=a&pair=b+c");
+    assert_eq!(qs::from_str::<Query>(&encoded).unwrap(), q);
+
+    // Top-level sequences are always indexed.
+    assert_eq!(config.serialize_string(&vec![1, 2]).unwrap(), "0=1&1=2");
+
+    #[derive(Serialize)]
+    struct Point {
+        x: u8,
+        y: u8,
+    }
+
+    #[derive(Serialize)]
+    struct Nested {
+        ps: Vec<Point>,
+        grid: Vec<Vec<u8>>,
+    }
+
+    let points = Nested {
+        ps: vec![Point { x: 1, y: 2 }],
+        grid: vec![],
+    };
+    let grid = Nested {
+        ps: vec![],
+        grid: vec![vec![1]],
+    };
+    for &format in &[ArrayFormat::Brackets, ArrayFormat::Repeat] {
+        let config = config.array_format(format);
+        config
+            .serialize_string(&points)
+            .expect_err("sequence of structs must be indexed");
+        config
+            .serialize_string(&grid)
+            .expect_err("sequence of sequences must be indexed");
+    }
+    assert_eq!(
+        Config::default().serialize_string(&points).unwrap(),
+        "ps[0][x]=1&ps[0][y]=2"
+    );
+}
+
+#[test]
+fn testing_helpers() {
+    use qs::testing::{assert_round_trip, assert_serializes_to};
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Query {
+        name: String,
+        tags: Vec<String>,
+    }
+
+    let q = Query {
+        name: "a b".to_owned(),
+        tags: vec!["x".to_owned()],
+    };
+    assert_serializes_to(&q, "name=a+b&tags[0]=x");
+    assert_serializes_to(3, "3");
+    assert_round_trip(q);
+
+    let result = std::panic::catch_unwind(|| assert_serializes_to(1, "2"));
+    assert!(result.is_err());
+    // Empty sequences are lost, so they do not round trip.
+    let result = std::panic::catch_unwind(|| {
+        assert_round_trip(Query {
+            name: "a".to_owned(),
+            tags: vec![],
+        })
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+fn serialize_to_bytes_and_writer() {
+    #[derive(Serialize)]
+    struct Query {
+        name: &'static str,
+        ids: Vec<u8>,
+    }
+
+    let q = Query {
+        name: "a b",
+        ids: vec![1, 2],
+    };
+    let expected = "name=a+b&ids[0]=1&ids[1]=2";
+    assert_eq!(qs::to_bytes(&q).unwrap(), expected.as_bytes());
+
+    /// Records each write separately.
+    struct Writes(Vec<Vec<u8>>);
+
+    impl std::io::Write for Writes {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.push(buf.to_vec());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut writes = Writes(Vec::new());
+    qs::to_writer(&q, &mut writes).unwrap();
+    assert!(writes.0.len() >= 3, "pairs are written incrementally");
+    assert_eq!(writes.0.concat(), expected.as_bytes());
+}
+
+#[test]
+fn serialize_leading_question_mark() {
+    #[derive(Serialize)]
+    struct Query {
+        page: u32,
+        tags: Vec<&'static str>,
+    }
+
+    #[derive(Serialize)]
+    struct Empty {}
+
+    let q = Query {
+        page: 2,
+        tags: vec!["a"],
+    };
+    assert_eq!(qs::to_string(&q).unwrap(), "page=2&tags[0]=a");
+
+    let config = qs::Config::default().include_leading_question_mark(true);
+    let encoded = config.serialize_string(&q).unwrap();
+    assert_eq!(encoded, "?page=2&tags[0]=a");
+    assert_eq!(config.serialize_string(&Empty {}).unwrap(), "");
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Rec {
+        page: u32,
+        tags: Vec<String>,
+    }
+    assert_eq!(
+        qs::from_str::<Rec>(&encoded).unwrap(),
+        Rec {
+            page: 2,
+            tags: vec!["a".to_owned()],
+        }
+    );
+}
+
+#[test]
+fn serialize_generic_struct() {
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Page<T: serde::Serialize> {
+        data: T,
+        page: u32,
+    }
+
+    #[derive(Serialize)]
+    struct User {
+        name: &'static str,
+        roles: Vec<&'static str>,
+    }
+
+    let scalar = Page { data: 5, page: 1 };
+    assert_eq!(qs::to_string(&scalar).unwrap(), "data=5&page=1");
+
+    let nested = Page {
+        data: User {
+            name: "Alice",
+            roles: vec!["admin"],
+        },
+        page: 2,
+    };
+    assert_eq!(
+        qs::to_string(&nested).unwrap(),
+        "data[name]=Alice&data[roles][0]=admin&page=2"
+    );
+
+    let list = Page {
+        data: vec![Page { data: "x", page: 3 }],
+        page: 4,
+    };
+    assert_eq!(
+        qs::to_string(&list).unwrap(),
+        "data[0][data]=x&data[0][page]=3&page=4"
+    );
+
+    let mut map = BTreeMap::new();
+    map.insert("b", Some(1));
+    map.insert("a", None);
+    let map = Page { data: map, page: 5 };
+    assert_eq!(qs::to_string(&map).unwrap(), "data[b]=1&page=5");
+}
+
+#[test]
+fn form_content_type() {
+    assert_eq!(qs::FORM_CONTENT_TYPE, "application/x-www-form-urlencoded");
+    assert!(qs::FORM_CONTENT_TYPE_WITH_CHARSET.starts_with(qs::FORM_CONTENT_TYPE));
+    assert!(qs::FORM_CONTENT_TYPE_WITH_CHARSET.ends_with("; charset=utf-8"));
+}
+
+#[test]
+fn serialize_borrowed_str_keys() {
+    use std::collections::{BTreeMap, HashMap};
+
+    let mut map: HashMap<&str, &str> = HashMap::new();
+    map.insert("first name", "John");
+    assert_eq!(qs::to_string(&map).unwrap(), "first+name=John");
+
+    let mut inner: BTreeMap<&str, &str> = BTreeMap::new();
+    inner.insert("a&b", "1");
+    inner.insert("c", "2");
+    let mut outer: BTreeMap<&str, BTreeMap<&str, &str>> = BTreeMap::new();
+    outer.insert("x", inner);
+    assert_eq!(qs::to_string(&outer).unwrap(), "x[a%26b]=1&x[c]=2");
+
+    /// Serializes its entries with separate `serialize_key` and
+    /// `serialize_value` calls.
+    struct KeyThenValue<K>(Vec<(K, u8)>);
+
+    impl<K: serde::Serialize> serde::Serialize for KeyThenValue<K> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            use serde::ser::SerializeMap;
+
+            let mut map = serializer.serialize_map(Some(self.0.len()))?;
+            for (key, value) in &self.0 {
+                map.serialize_key(key)?;
+                map.serialize_value(value)?;
+            }
+            map.end()
+        }
+    }
+
+    let entries = KeyThenValue(vec![("a b", 1), ("c", 2)]);
+    assert_eq!(qs::to_string(&entries).unwrap(), "a+b=1&c=2");
+    assert_eq!(qs::to_string(&KeyThenValue(vec![(7, 1)])).unwrap(), "7=1");
+    let mut nested = BTreeMap::new();
+    nested.insert("x", KeyThenValue(vec![("y", 1)]));
+    assert_eq!(qs::to_string(&nested).unwrap(), "x[y]=1");
+    qs::to_string(&KeyThenValue(vec![((), 1)])).expect_err("unit keys are unsupported");
+}
+
+#[test]
+fn serialize_screaming_snake_case() {
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+    enum Role {
+        SuperUser,
+        Guest,
+    }
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+    struct Query {
+        first_name: String,
+        user_role: Role,
+        #[serde(rename = "id")]
+        user_id: u32,
+    }
+
+    let query = Query {
+        first_name: "Alice".to_owned(),
+        user_role: Role::SuperUser,
+        user_id: 7,
+    };
+    let encoded = qs::to_string(&query).unwrap();
+    assert_eq!(encoded, "FIRST_NAME=Alice&USER_ROLE=SUPER_USER&id=7");
+    assert_eq!(qs::from_str::<Query>(&encoded).unwrap(), query);
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "tokio")]
+
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_qs as qs;
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncRead, ReadBuf};
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Query {
+    name: String,
+    ids: Vec<u8>,
+}
+
+/// Yields its input a few bytes at a time, like a streamed request body.
+struct Chunked<'a>(&'a [u8]);
+
+impl AsyncRead for Chunked<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let n = self.0.len().min(3).min(buf.remaining());
+        buf.put_slice(&self.0[..n]);
+        self.0 = &self.0[n..];
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[test]
+fn from_async_reader() {
+    futures::executor::block_on(async {
+        let rec: Query = qs::from_async_reader(Chunked(b"name=Acme&ids[0]=1&ids[1]=2"))
+            .await
+            .unwrap();
+        assert_eq!(
+            rec,
+            Query {
+                name: "Acme".to_owned(),
+                ids: vec![1, 2],
+            }
+        );
+
+        qs::from_async_reader::<Query, _>(Chunked(b"name=Acme&ids[0]=x"))
+            .await
+            .expect_err("invalid id");
+    })
+}
+
+#[test]
+fn from_async_reader_with_config() {
+    futures::executor::block_on(async {
+        let config = qs::Config::new(5, false);
+        let rec: Query =
+            qs::from_async_reader_with_config(Chunked(b"name=Acme&ids%5B0%5D=1"), &config)
+                .await
+                .unwrap();
+        assert_eq!(rec.ids, [1]);
+    })
+}
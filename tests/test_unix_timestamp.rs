@@ -0,0 +1,85 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_qs as qs;
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+struct Query {
+    #[serde(with = "qs::unix_timestamp")]
+    at: SystemTime,
+}
+
+fn at(secs: i64, nanos: u32) -> Query {
+    let at = if secs >= 0 {
+        UNIX_EPOCH + Duration::from_secs(secs as u64)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs())
+    };
+    Query {
+        at: at + Duration::from_nanos(nanos.into()),
+    }
+}
+
+#[test]
+fn serialize_unix_timestamp() {
+    assert_eq!(qs::to_string(&at(0, 0)).unwrap(), "at=0");
+    assert_eq!(
+        qs::to_string(&at(1622548800, 999)).unwrap(),
+        "at=1622548800"
+    );
+    assert_eq!(qs::to_string(&at(-10, 0)).unwrap(), "at=-10");
+    assert_eq!(qs::to_string(&at(-10, 1)).unwrap(), "at=-10");
+    assert_eq!(qs::to_string(&at(-11, 999_999_999)).unwrap(), "at=-11");
+}
+
+#[test]
+fn deserialize_unix_timestamp() {
+    assert_eq!(
+        qs::from_str::<Query>("at=1622548800").unwrap(),
+        at(1622548800, 0)
+    );
+    assert_eq!(qs::from_str::<Query>("at=-86400").unwrap(), at(-86400, 0));
+    qs::from_str::<Query>("at=").expect_err("empty");
+    qs::from_str::<Query>("at=soon").expect_err("not a timestamp");
+}
+
+#[test]
+fn deserialize_rfc3339() {
+    let cases = [
+        ("1970-01-01T00:00:00Z", 0, 0),
+        ("2021-06-01T12:00:00Z", 1622548800, 0),
+        ("2021-06-01t12:00:00z", 1622548800, 0),
+        ("2021-06-01%2012:00:00Z", 1622548800, 0),
+        ("2021-06-01T14:30:00%2B02:30", 1622548800, 0),
+        ("2021-06-01T07:00:00-05:00", 1622548800, 0),
+        ("2021-06-01T12:00:00.5Z", 1622548800, 500_000_000),
+        ("2021-06-01T12:00:00.1234567891Z", 1622548800, 123_456_789),
+        ("2000-02-29T00:00:00Z", 951782400, 0),
+        ("1969-12-31T23:59:59Z", -1, 0),
+        ("1900-01-01T00:00:00Z", -2208988800, 0),
+    ];
+    for &(input, secs, nanos) in cases.iter() {
+        assert_eq!(
+            qs::from_str::<Query>(&format!("at={}", input)).unwrap(),
+            at(secs, nanos),
+            "{}",
+            input
+        );
+    }
+
+    let invalid = [
+        "2021-06-01",
+        "2021-06-01T12:00:00",
+        "2021-06-01T12:00:00+02:00",
+        "2021-13-01T12:00:00Z",
+        "2021-02-29T12:00:00Z",
+        "2021-06-01T24:00:00Z",
+        "2021-06-01T12:00:00.Z",
+        "2021-06-01T12:00:00%2B0200",
+        "2021-06-01T12:00:00ZZ",
+    ];
+    for input in invalid.iter() {
+        qs::from_str::<Query>(&format!("at={}", input)).expect_err(input);
+    }
+}
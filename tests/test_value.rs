@@ -0,0 +1,43 @@
+extern crate serde_qs as qs;
+
+use qs::QsValue;
+use std::collections::BTreeMap;
+
+#[test]
+fn deserialize_value() {
+    let value: QsValue = qs::from_str("a[b]=1&a[c][]=x&a[c][]=y&d=&e[0][f]=2").unwrap();
+
+    let a = value.get("a").unwrap();
+    assert_eq!(a.get("b").and_then(QsValue::as_str), Some("1"));
+    assert_eq!(
+        a.get("c").and_then(QsValue::as_seq),
+        Some(&vec![QsValue::from("x"), QsValue::from("y")])
+    );
+    assert_eq!(value.get("d"), Some(&QsValue::from("")));
+    let e = value.get("e").and_then(QsValue::as_seq).unwrap();
+    assert_eq!(e[0].get("f"), Some(&QsValue::from("2")));
+
+    assert_eq!(value.get("missing"), None);
+    assert_eq!(value.as_str(), None);
+    assert_eq!(QsValue::from("x").get("a"), None);
+
+    let empty: QsValue = qs::from_str("").unwrap();
+    assert_eq!(empty, QsValue::Map(BTreeMap::new()));
+}
+
+#[test]
+fn serialize_value() {
+    let mut inner = BTreeMap::new();
+    inner.insert("b".to_owned(), QsValue::from("1 2"));
+    inner.insert(
+        "c".to_owned(),
+        QsValue::Seq(vec![QsValue::from("x"), QsValue::from("y")]),
+    );
+    let mut map = BTreeMap::new();
+    map.insert("a".to_owned(), QsValue::Map(inner));
+    let value = QsValue::Map(map);
+
+    let encoded = qs::to_string(&value).unwrap();
+    assert_eq!(encoded, "a[b]=1+2&a[c][0]=x&a[c][1]=y");
+    assert_eq!(qs::from_str::<QsValue>(&encoded).unwrap(), value);
+}